@@ -39,11 +39,13 @@ struct Subscription {
 #[derive(Deserialize, Debug)]
 struct StreamEvent {
     broadcaster_user_id: String,
+    broadcaster_user_login: String,
 }
 
 #[derive(Deserialize, Debug)]
 struct ChannelUpdateEvent {
     broadcaster_user_id: String,
+    broadcaster_user_login: String,
     title: String,
     category_name: String,
 }
@@ -345,6 +347,7 @@ async fn function_handler(request: Request) -> Result<Response<Body>, Error> {
                 Ok(Some((_name, title, category, _old_live, updated))) => {
                     json!({
                         "broadcaster_id": event.broadcaster_user_id.clone(),
+                        "broadcaster_login": event.broadcaster_user_login.clone(),
                         "category": category,
                         "title": title,
                         "is_live": is_live,
@@ -355,6 +358,7 @@ async fn function_handler(request: Request) -> Result<Response<Body>, Error> {
                 Ok(None) => {
                     json!({
                         "broadcaster_id": event.broadcaster_user_id.clone(),
+                        "broadcaster_login": event.broadcaster_user_login.clone(),
                         "category": "",
                         "title": "",
                         "is_live": is_live,
@@ -376,6 +380,7 @@ async fn function_handler(request: Request) -> Result<Response<Body>, Error> {
                 Ok(Some((_name, _title, _category, existing_live, updated))) => {
                     json!({
                         "broadcaster_id": event.broadcaster_user_id.clone(),
+                        "broadcaster_login": event.broadcaster_user_login.clone(),
                         "category": event.category_name,
                         "title": event.title,
                         "is_live": existing_live,
@@ -386,6 +391,7 @@ async fn function_handler(request: Request) -> Result<Response<Body>, Error> {
                 Ok(None) => {
                     json!({
                         "broadcaster_id": event.broadcaster_user_id.clone(),
+                        "broadcaster_login": event.broadcaster_user_login.clone(),
                         "category": event.category_name,
                         "title": event.title,
                         "is_live": false,
@@ -409,9 +415,10 @@ async fn function_handler(request: Request) -> Result<Response<Body>, Error> {
     };
 
     let mutation = r#"
-    mutation UpdateStreamer($broadcaster_id: String!, $category: String!, $title: String!, $is_live: Boolean!, $updated: AWSDateTime!, $type: String!) {
+    mutation UpdateStreamer($broadcaster_id: String!, $broadcaster_login: String, $category: String!, $title: String!, $is_live: Boolean!, $updated: AWSDateTime!, $type: String!) {
     updateStreamer(
         broadcaster_id: $broadcaster_id,
+        broadcaster_login: $broadcaster_login,
         category: $category,
         title: $title,
         is_live: $is_live,
@@ -420,6 +427,7 @@ async fn function_handler(request: Request) -> Result<Response<Body>, Error> {
     ) {
             broadcaster_id
             broadcaster_name
+            broadcaster_login
             category
             title
             is_live
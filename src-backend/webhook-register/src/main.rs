@@ -71,6 +71,7 @@ struct StreamInfo {
     status: String,
     game_name: String,
     user_id: String,
+    user_login: String,
     user_name: String,
     title: String,
 }
@@ -78,6 +79,7 @@ struct StreamInfo {
 #[derive(Deserialize, Debug, Clone)]
 struct Streams {
     user_id: String,
+    user_login: String,
     user_name: String,
     game_name: String,
     is_live: bool,
@@ -88,6 +90,7 @@ struct Streams {
 #[derive(Serialize, Deserialize, Debug)]
 struct Channels {
     broadcaster_id: String,
+    broadcaster_login: String,
     broadcaster_name: String,
     game_name: String,
     title: String,
@@ -248,6 +251,7 @@ async fn ids_exist(
                         streams.push(Streams {
                             game_name: item.game_name.to_string(),
                             user_id: item.user_id.to_string(),
+                            user_login: item.user_login.to_string(),
                             user_name: item.user_name.to_string(),
                             is_live: if item.status == "live" { true } else { false },
                             title: item.title.to_string(),
@@ -281,6 +285,7 @@ async fn ids_exist(
                     for ch in channels.data {
                         streams.push(Streams {
                             user_id: ch.broadcaster_id,
+                            user_login: ch.broadcaster_login,
                             user_name: ch.broadcaster_name,
                             game_name: ch.game_name,
                             is_live: false,
@@ -340,6 +345,10 @@ async fn ids_exist(
                     "broadcaster_name".to_string(),
                     AttributeValue::S(streamer.user_name.to_string()),
                 );
+                item.insert(
+                    "broadcaster_login".to_string(),
+                    AttributeValue::S(streamer.user_login.to_string()),
+                );
                 item.insert(
                     "category".to_string(),
                     AttributeValue::S(streamer.game_name.to_string()),
@@ -0,0 +1,459 @@
+use super::protocol::StreamerUpdate;
+use super::state::{stream_ended, stream_started, stream_uptime};
+use super::worker::AppSyncWorker;
+use crate::favorites::is_favorite;
+use crate::history::{record, HistoryEntry, HistoryKind, Outcome};
+use crate::image_cache::attach_images;
+use crate::notifications::{notify, NotificationRequest};
+use crate::quiet::{defer, is_quiet, DeferredNotification};
+use crate::rules::{evaluate_rules, RuleEvent};
+use crate::settings::current_settings;
+use crate::snooze::is_silenced;
+use crate::templates::{render_template, TemplateContext};
+use crate::watchlist::WatchList;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Manager;
+
+// Every notification goes through here: pause, snoozes, rules, cooldowns and
+// merging, then quiet hours, and only then the platform toast. Channel
+// updates meet the rules once merged; favorites skip the rules and quiet
+// hours. Each outcome is kept in the history.
+pub fn notify_update(worker: &mut AppSyncWorker, update: &StreamerUpdate) {
+  let (name, update_type) = match (
+    update.broadcaster_name.as_deref(),
+    update.update_type.as_deref(),
+  ) {
+    (Some(name), Some(update_type)) => (name, update_type),
+    _ => return,
+  };
+  if !matches!(update_type, "status" | "channel_updated" | "offline") {
+    return;
+  }
+
+  // Track transitions before anything can bail out, so durations stay right
+  // even for streams we didn't notify about.
+  let settings = current_settings(&worker.app_handle);
+  let mut ran_for = None;
+  match update_type {
+    "status" => stream_started(&worker.app_handle, &update.broadcaster_id),
+    "offline" => {
+      ran_for = stream_ended(&worker.app_handle, &update.broadcaster_id);
+      if !settings
+        .offline_notifications
+        .contains(&update.broadcaster_id)
+      {
+        return;
+      }
+    }
+    _ => {
+      // Title re-sends and language or label edits aren't worth a toast.
+      if channel_changes(update).is_empty() {
+        println!("Channel update for {} changed nothing, skipping.", name);
+        return;
+      }
+    }
+  }
+
+  if worker.paused {
+    record_outcome(worker, update, Outcome::Suppressed, Some("paused"));
+    return;
+  }
+
+  if is_silenced(&worker.app_handle, &update.broadcaster_id) {
+    record_outcome(worker, update, Outcome::Suppressed, Some("snoozed"));
+    return;
+  }
+
+  // Channel updates are checked once they're merged.
+  if update_type != "channel_updated" && suppressed_by_rules(worker, update) {
+    return;
+  }
+
+  match update_type {
+    "status" => {
+      let cooldown =
+        Duration::from_secs(settings.live_cooldown_minutes.saturating_mul(60));
+      if !worker.throttle.allow_live(&update.broadcaster_id, cooldown) {
+        println!("{} went live again within the cooldown, skipping.", name);
+        record_outcome(worker, update, Outcome::Suppressed, Some("cooldown"));
+        return;
+      }
+      // The go-live already shows the current title and category.
+      if let Some(pending) = worker
+        .throttle
+        .cancel_channel_update(&update.broadcaster_id)
+      {
+        record_outcome(
+          worker,
+          &pending.update,
+          Outcome::Suppressed,
+          Some("went live"),
+        );
+      }
+      deliver(worker, update, 1, None);
+    }
+    "offline" => deliver(worker, update, 1, ran_for),
+    _ => {
+      let window = Duration::from_secs(settings.channel_update_window_secs);
+      if window.is_zero() {
+        send_channel_update(worker, update, 1);
+      } else if worker.throttle.queue_channel_update(update.clone(), window) {
+        record_outcome(worker, update, Outcome::Suppressed, Some("merged"));
+      }
+    }
+  }
+}
+
+// Sends channel updates whose merge window has passed.
+pub fn flush_channel_updates(worker: &mut AppSyncWorker) {
+  for pending in worker.throttle.take_due_channel_updates() {
+    if worker.paused {
+      record_outcome(
+        worker,
+        &pending.update,
+        Outcome::Suppressed,
+        Some("paused"),
+      );
+      continue;
+    }
+    // Snoozed while the update waited out its window.
+    if is_silenced(&worker.app_handle, &pending.update.broadcaster_id) {
+      record_outcome(
+        worker,
+        &pending.update,
+        Outcome::Suppressed,
+        Some("snoozed"),
+      );
+      continue;
+    }
+    // A burst can end where it started.
+    if channel_changes(&pending.update).is_empty() {
+      record_outcome(
+        worker,
+        &pending.update,
+        Outcome::Suppressed,
+        Some("no changes"),
+      );
+      continue;
+    }
+    send_channel_update(worker, &pending.update, pending.merged);
+  }
+}
+
+// Rules see the channel update as it will be shown, after merging.
+fn send_channel_update(
+  worker: &AppSyncWorker,
+  update: &StreamerUpdate,
+  merged: u32,
+) {
+  if suppressed_by_rules(worker, update) {
+    return;
+  }
+  deliver(worker, update, merged, None);
+}
+
+// Records and reports whether the user's rules drop the update. Favorites
+// are always let through.
+fn suppressed_by_rules(
+  worker: &AppSyncWorker,
+  update: &StreamerUpdate,
+) -> bool {
+  let name = update.broadcaster_name.as_deref().unwrap_or_default();
+  let verdict = evaluate_rules(
+    &worker.app_handle,
+    &RuleEvent {
+      broadcaster_id: update.broadcaster_id.clone(),
+      broadcaster_name: name.to_string(),
+      category: update.category.clone().unwrap_or_default(),
+      title: update.title.clone().unwrap_or_default(),
+      event_type: update.update_type.clone().unwrap_or_default(),
+    },
+  );
+  if verdict.notify || is_favorite(&worker.app_handle, &update.broadcaster_id) {
+    return false;
+  }
+  let reason = format!("rule '{}'", verdict.rule_name.unwrap_or_default());
+  println!("Notification for {} suppressed by {}.", name, reason);
+  record_outcome(worker, update, Outcome::Suppressed, Some(&reason));
+  true
+}
+
+fn deliver(
+  worker: &AppSyncWorker,
+  update: &StreamerUpdate,
+  merged: u32,
+  ran_for: Option<Duration>,
+) {
+  let name = update.broadcaster_name.as_deref().unwrap_or_default();
+  let update_type = update.update_type.as_deref().unwrap_or_default();
+  let favorite = is_favorite(&worker.app_handle, &update.broadcaster_id);
+
+  if !favorite && is_quiet(&worker.app_handle) {
+    defer(DeferredNotification {
+      broadcaster_id: update.broadcaster_id.clone(),
+      broadcaster_login: login_for(worker, update),
+      broadcaster_name: name.to_string(),
+      update_type: update_type.to_string(),
+    });
+    record_outcome(worker, update, Outcome::Deferred, Some("quiet time"));
+    return;
+  }
+
+  let (heading, msg) = compose(worker, update, merged, ran_for);
+  let request = NotificationRequest {
+    title: heading,
+    body: msg,
+    broadcaster_id: update.broadcaster_id.clone(),
+    broadcaster_login: login_for(worker, update),
+    broadcaster_name: name.to_string(),
+    avatar: None,
+    thumbnail: None,
+    // A stream ending isn't worth keeping on screen.
+    priority: favorite && update_type != "offline",
+  };
+  let avatar_url = worker
+    .known_streamers
+    .get(&update.broadcaster_id)
+    .and_then(|b| b.profile_picture.clone());
+  let with_thumbnail = update_type == "status";
+  let app = worker.app_handle.clone();
+  let mut entry = history_entry(
+    update,
+    HistoryKind::Notification,
+    Some(Outcome::Shown),
+    None,
+  );
+  tauri::async_runtime::spawn(async move {
+    let request =
+      attach_images(&app, request, avatar_url, with_thumbnail).await;
+    if let Err(e) = notify(&app, request).await {
+      entry.outcome = Some(Outcome::Suppressed);
+      entry.reason = Some(format!("delivery failed: {}", e));
+    }
+    record(&app, entry);
+  });
+}
+
+// The user's template for the event if there is one, the built-in text
+// otherwise.
+fn compose(
+  worker: &AppSyncWorker,
+  update: &StreamerUpdate,
+  merged: u32,
+  ran_for: Option<Duration>,
+) -> (String, String) {
+  let update_type = update.update_type.as_deref().unwrap_or_default();
+  let context = template_context(worker, update, ran_for);
+  if let Some(rendered) = render_template(
+    &worker.app_handle,
+    update_type,
+    &update.broadcaster_id,
+    &context,
+  ) {
+    return (rendered.heading, rendered.body);
+  }
+
+  let TemplateContext {
+    name,
+    category,
+    title,
+    ..
+  } = &context;
+  match update_type {
+    "offline" => {
+      let mut parts = Vec::new();
+      if let Some(ran_for) = ran_for {
+        parts.push(format!("Streamed for {}", format_duration(ran_for)));
+      }
+      if !category.is_empty() {
+        parts.push(format!("Last category: {}", category));
+      }
+      (format!("{} went offline", name), parts.join(" - "))
+    }
+    "channel_updated" => {
+      let heading = if merged > 1 {
+        format!("{} - Channel updated ({} changes)", name, merged)
+      } else {
+        format!("{} - Channel updated", name)
+      };
+      let changes = channel_changes(update);
+      // Without a previous value we can only show the new ones.
+      let body = if changes.iter().any(|c| c.before.is_none()) {
+        format!("{} - {}", category, title)
+      } else {
+        changes
+          .iter()
+          .map(|c| match c.field {
+            ChannelField::Category => format!(
+              "Category: {} → {}",
+              c.before.unwrap_or_default(),
+              c.after
+            ),
+            ChannelField::Title => format!("Title: {}", c.after),
+          })
+          .collect::<Vec<_>>()
+          .join("\n")
+      };
+      (heading, body)
+    }
+    _ => (
+      format!("{} just went live!", name),
+      format!("{} - {}", category, title),
+    ),
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ChannelField {
+  Category,
+  Title,
+}
+
+struct ChannelChange<'a> {
+  field: ChannelField,
+  // None when we never saw the old value.
+  before: Option<&'a str>,
+  after: &'a str,
+}
+
+// What a channel update changed compared to what we knew before it. Fields
+// the update doesn't carry count as unchanged.
+fn channel_changes(update: &StreamerUpdate) -> Vec<ChannelChange<'_>> {
+  let fields = [
+    (
+      ChannelField::Category,
+      update.previous_category.as_deref(),
+      update.category.as_deref(),
+    ),
+    (
+      ChannelField::Title,
+      update.previous_title.as_deref(),
+      update.title.as_deref(),
+    ),
+  ];
+
+  fields
+    .into_iter()
+    .filter_map(|(field, before, after)| {
+      let after = after?;
+      let before = before.filter(|b| !b.trim().is_empty());
+      if before.is_some_and(|b| b.trim() == after.trim()) {
+        return None;
+      }
+      Some(ChannelChange {
+        field,
+        before,
+        after,
+      })
+    })
+    .collect()
+}
+
+fn template_context(
+  worker: &AppSyncWorker,
+  update: &StreamerUpdate,
+  ran_for: Option<Duration>,
+) -> TemplateContext {
+  let known = worker.known_streamers.get(&update.broadcaster_id);
+  let name = update.broadcaster_name.clone().unwrap_or_default();
+  // Offline events don't always carry the category, the last update does.
+  let field = |value: &Option<String>, fallback: Option<&String>| {
+    value
+      .clone()
+      .filter(|v| !v.is_empty())
+      .or_else(|| fallback.cloned())
+      .unwrap_or_default()
+  };
+  let uptime = ran_for
+    .or_else(|| stream_uptime(&worker.app_handle, &update.broadcaster_id));
+
+  TemplateContext {
+    login: login_for(worker, update),
+    category: field(&update.category, known.map(|b| &b.category)),
+    title: field(&update.title, known.map(|b| &b.title)),
+    previous_title: update.previous_title.clone().unwrap_or_default(),
+    previous_category: update.previous_category.clone().unwrap_or_default(),
+    uptime: uptime.map(format_duration).unwrap_or_default(),
+    time: chrono::Local::now().format("%H:%M").to_string(),
+    name,
+  }
+}
+
+// The login from the update or the last snapshot. Streamers stored before
+// logins were kept fall back to their watch list entry, then to the display
+// name, which only differs from the login in case for most of them.
+fn login_for(worker: &AppSyncWorker, update: &StreamerUpdate) -> String {
+  let broadcaster_id = &update.broadcaster_id;
+  let known = worker
+    .known_streamers
+    .get(broadcaster_id)
+    .map(|b| b.broadcaster_login.clone());
+  update
+    .broadcaster_login
+    .clone()
+    .into_iter()
+    .chain(known)
+    .find(|login| !login.is_empty())
+    .or_else(|| {
+      let list = worker.app_handle.try_state::<Mutex<WatchList>>()?;
+      let list = list.lock().unwrap();
+      list
+        .channels
+        .iter()
+        .find(|c| &c.broadcaster_id == broadcaster_id)
+        .map(|c| c.login.clone())
+    })
+    .unwrap_or_else(|| {
+      update
+        .broadcaster_name
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase()
+    })
+}
+
+pub fn format_duration(duration: Duration) -> String {
+  let minutes = duration.as_secs() / 60;
+  match (minutes / 60, minutes % 60) {
+    (0, m) => format!("{}m", m),
+    (h, m) => format!("{}h {}m", h, m),
+  }
+}
+
+fn history_entry(
+  update: &StreamerUpdate,
+  kind: HistoryKind,
+  outcome: Option<Outcome>,
+  reason: Option<&str>,
+) -> HistoryEntry {
+  HistoryEntry {
+    kind,
+    broadcaster_id: update.broadcaster_id.clone(),
+    broadcaster_name: update.broadcaster_name.clone().unwrap_or_default(),
+    event_type: update.update_type.clone().unwrap_or_default(),
+    category: update.category.clone().unwrap_or_default(),
+    title: update.title.clone().unwrap_or_default(),
+    outcome,
+    reason: reason.map(String::from),
+  }
+}
+
+pub fn record_event(worker: &AppSyncWorker, update: &StreamerUpdate) {
+  record(
+    &worker.app_handle,
+    history_entry(update, HistoryKind::Event, None, None),
+  );
+}
+
+fn record_outcome(
+  worker: &AppSyncWorker,
+  update: &StreamerUpdate,
+  outcome: Outcome,
+  reason: Option<&str>,
+) {
+  record(
+    &worker.app_handle,
+    history_entry(update, HistoryKind::Notification, Some(outcome), reason),
+  );
+}
@@ -0,0 +1,161 @@
+use super::state::stream_started;
+use super::worker::AppSyncWorker;
+use crate::command::Broadcasters;
+use crate::config::AppConfig;
+use crate::twitch::{
+  fetch_streamer_snapshots, register_streamers_webhook, Broadcaster,
+};
+use crate::watchlist::watched_ids;
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::Emitter;
+
+// Remembers the follow list and what has already been registered with the
+// backend, so a reload only has to deal with the difference.
+#[derive(Default)]
+pub struct FollowTracker {
+  followed: Option<Vec<String>>,
+  registered: HashSet<String>,
+}
+
+#[derive(Default)]
+pub struct FollowChanges {
+  pub added: Vec<String>,
+  pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct FollowsAddedEvent {
+  broadcaster_ids: Vec<String>,
+  streamers: Vec<Broadcasters>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct FollowsRemovedEvent {
+  broadcaster_ids: Vec<String>,
+}
+
+impl FollowTracker {
+  pub fn followed(&self) -> &[String] {
+    self.followed.as_deref().unwrap_or(&[])
+  }
+
+  // The first list we see is the baseline, not a batch of new follows.
+  pub fn update(&mut self, followed: Vec<String>) -> FollowChanges {
+    let changes = match &self.followed {
+      Some(previous) => {
+        let previous: HashSet<&String> = previous.iter().collect();
+        let current: HashSet<&String> = followed.iter().collect();
+        FollowChanges {
+          added: current
+            .difference(&previous)
+            .map(|s| s.to_string())
+            .collect(),
+          removed: previous
+            .difference(&current)
+            .map(|s| s.to_string())
+            .collect(),
+        }
+      }
+      None => FollowChanges::default(),
+    };
+
+    self.followed = Some(followed);
+    changes
+  }
+}
+
+// Registers broadcasters the backend hasn't been told about yet. Failures are
+// left unmarked so the next reload tries them again.
+pub async fn register_new_broadcasters(
+  config: &AppConfig,
+  tracker: &mut FollowTracker,
+  broadcaster_ids: &[String],
+) {
+  let new_ids: Vec<&String> = broadcaster_ids
+    .iter()
+    .filter(|id| !tracker.registered.contains(*id))
+    .collect();
+  if new_ids.is_empty() {
+    return;
+  }
+
+  let broadcasters: Vec<Broadcaster> = new_ids
+    .iter()
+    .filter_map(|id| match id.parse::<u64>() {
+      Ok(parsed_id) => Some(Broadcaster {
+        broadcaster_id: parsed_id,
+      }),
+      Err(e) => {
+        eprintln!("Skipping invalid broadcaster id '{}': {}", id, e);
+        None
+      }
+    })
+    .collect();
+
+  match register_streamers_webhook(config, broadcasters).await {
+    Ok(()) => {
+      println!("Registered {} new broadcaster(s).", new_ids.len());
+      tracker.registered.extend(new_ids.into_iter().cloned());
+    }
+    Err(e) => eprintln!("{} Will retry on the next reload.", e),
+  }
+}
+
+pub async fn announce_follow_changes(
+  worker: &mut AppSyncWorker,
+  changes: FollowChanges,
+) {
+  // Manually watched channels stay in the list whether followed or not.
+  let watched = watched_ids(&worker.app_handle);
+  let added: Vec<String> = changes
+    .added
+    .into_iter()
+    .filter(|id| !watched.contains(id))
+    .collect();
+  let removed: Vec<String> = changes
+    .removed
+    .into_iter()
+    .filter(|id| !watched.contains(id))
+    .collect();
+
+  if !added.is_empty() {
+    println!("Detected {} new follow(s).", added.len());
+    let streamers = fetch_streamer_snapshots(&worker.config, &added)
+      .await
+      .unwrap_or_else(|e| {
+        eprintln!("Failed to fetch new follows: {}", e);
+        Vec::new()
+      });
+    for streamer in &streamers {
+      if streamer.is_live {
+        stream_started(&worker.app_handle, &streamer.broadcaster_id);
+      }
+      worker
+        .known_streamers
+        .insert(streamer.broadcaster_id.clone(), streamer.clone());
+    }
+
+    let event = FollowsAddedEvent {
+      broadcaster_ids: added,
+      streamers,
+    };
+    if let Err(e) = worker.app_handle.emit("follows:added", event) {
+      eprintln!("Error emitting 'follows:added' event: {}", e);
+    }
+  }
+
+  if !removed.is_empty() {
+    println!("Detected {} unfollow(s).", removed.len());
+    for id in &removed {
+      worker.known_streamers.remove(id);
+    }
+
+    let event = FollowsRemovedEvent {
+      broadcaster_ids: removed,
+    };
+    if let Err(e) = worker.app_handle.emit("follows:removed", event) {
+      eprintln!("Error emitting 'follows:removed' event: {}", e);
+    }
+  }
+}
@@ -1,52 +1,239 @@
+mod dispatch;
+mod follows;
 mod protocol;
+mod state;
 mod subscriptions;
+mod throttle;
 mod util;
 mod worker;
 
+use crate::token::SharedToken;
+use crate::AppHandle;
+use futures_util::FutureExt;
+use state::emit_state;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use std::time::Duration;
+use tauri::Manager;
+use tokio::sync::mpsc::{
+  unbounded_channel, UnboundedReceiver, UnboundedSender,
+};
+use tokio::sync::oneshot;
 use worker::AppSyncWorker;
 
+pub use state::{
+  connection_state, is_paused, subscription_health, ConnectionState, LiveSince,
+  WorkerStatus,
+};
+pub use subscriptions::SubscriptionHealth;
+
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(2);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(300);
+
 #[derive(Debug)]
 pub enum ControlMsg {
-  UpdateSubscriptions { streamer_ids: Vec<String> },
+  UpdateSubscriptions {
+    streamer_ids: Vec<String>,
+  },
+
+  // Re-merges the last known follows with the manual watch list.
+  RefreshSubscriptions,
+
+  // Keeps the socket and subscriptions alive but stops showing notifications.
+  Pause,
+  Resume,
+
+  ForceReconnect,
+
+  QueryStatus {
+    reply: oneshot::Sender<WorkerStatus>,
+  },
 
   Stop,
 }
 
-static CTRL_SENDER: OnceLock<Mutex<Option<UnboundedSender<ControlMsg>>>> =
-  OnceLock::new();
+struct WorkerHandle {
+  generation: u64,
+  sender: UnboundedSender<ControlMsg>,
+}
+
+static WORKER: OnceLock<Mutex<Option<WorkerHandle>>> = OnceLock::new();
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn worker_slot() -> &'static Mutex<Option<WorkerHandle>> {
+  WORKER.get_or_init(|| Mutex::new(None))
+}
 
 pub fn start_ws_client(
-  app_handle: tauri::AppHandle,
+  app_handle: AppHandle,
   token: String,
 ) -> Result<(), String> {
-  let sender_cell = CTRL_SENDER.get_or_init(|| Mutex::new(None));
-  let mut guard = sender_cell.lock().unwrap();
+  let mut guard = worker_slot().lock().unwrap();
 
   if guard.is_some() {
     return Err("Client is already running.".into());
   }
 
   let (tx, rx) = unbounded_channel();
-  *guard = Some(tx);
-
-  tauri::async_runtime::spawn(async move {
-    let worker = AppSyncWorker::new(app_handle, rx, token).await;
-    if let Err(e) = worker.run().await {
-      eprintln!("AppSync worker exited with an error: {}", e);
-    }
+  let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+  *guard = Some(WorkerHandle {
+    generation,
+    sender: tx,
   });
 
+  tauri::async_runtime::spawn(supervise(app_handle, rx, token, generation));
+
   Ok(())
 }
 
 pub fn stop_ws_client() -> Result<(), String> {
-  let sender_cell = CTRL_SENDER.get().ok_or("Client is not running.")?;
-  let guard = sender_cell.lock().unwrap();
-  let sender = guard.as_ref().ok_or("Client is not running.")?;
+  // Taking the handle frees the slot right away, so a new client can be
+  // started while this one is still shutting down.
+  let handle = worker_slot()
+    .lock()
+    .unwrap()
+    .take()
+    .ok_or("Client is not running.")?;
 
-  sender
+  handle
+    .sender
     .send(ControlMsg::Stop)
     .map_err(|e| format!("Failed to send stop signal: {}", e))
 }
+
+fn send_control(msg: ControlMsg) -> Result<(), String> {
+  let slot = worker_slot().lock().unwrap();
+  let handle = slot.as_ref().ok_or("Client is not running.")?;
+
+  handle
+    .sender
+    .send(msg)
+    .map_err(|e| format!("Failed to send control message: {}", e))
+}
+
+pub fn refresh_ws_subscriptions() -> Result<(), String> {
+  send_control(ControlMsg::RefreshSubscriptions)
+}
+
+pub fn pause_ws_client() -> Result<(), String> {
+  send_control(ControlMsg::Pause)
+}
+
+pub fn resume_ws_client() -> Result<(), String> {
+  send_control(ControlMsg::Resume)
+}
+
+pub fn reconnect_ws_client() -> Result<(), String> {
+  send_control(ControlMsg::ForceReconnect)
+}
+
+pub async fn query_ws_client() -> Result<WorkerStatus, String> {
+  let (reply, rx) = oneshot::channel();
+  send_control(ControlMsg::QueryStatus { reply })?;
+
+  rx.await
+    .map_err(|_| "Client stopped before answering.".to_string())
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+  panic
+    .downcast_ref::<&str>()
+    .copied()
+    .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+    .unwrap_or("unknown panic")
+}
+
+async fn supervise(
+  app_handle: AppHandle,
+  ctrl_rx: UnboundedReceiver<ControlMsg>,
+  token: String,
+  generation: u64,
+) {
+  *app_handle.state::<SharedToken>().0.write().await = token.clone();
+  // Building the worker is supervised too, or a panic here would leave the
+  // slot taken and the client could never be started again.
+  let worker =
+    AssertUnwindSafe(AppSyncWorker::new(app_handle.clone(), ctrl_rx, token))
+      .catch_unwind()
+      .await;
+  let mut worker = match worker {
+    Ok(worker) => worker,
+    Err(panic) => {
+      let error = format!("worker panicked: {}", panic_message(&panic));
+      eprintln!("AppSync worker failed to start: {}", error);
+      finish(&app_handle, generation, Some(error));
+      return;
+    }
+  };
+  let mut restarts: u32 = 0;
+  let mut last_error: Option<String> = None;
+
+  loop {
+    // A panic is treated like any other worker error instead of ending
+    // supervision.
+    let result = match AssertUnwindSafe(worker.run()).catch_unwind().await {
+      Ok(result) => result,
+      Err(panic) => Err(anyhow::anyhow!(
+        "worker panicked: {}",
+        panic_message(&panic)
+      )),
+    };
+
+    match result {
+      Ok(()) => {
+        // Stopped on request.
+        last_error = None;
+        break;
+      }
+      Err(e) => {
+        // A run that got connected was healthy; start the backoff over.
+        if std::mem::take(&mut worker.was_acked) {
+          restarts = 0;
+        }
+        restarts = restarts.saturating_add(1);
+        let delay = RESTART_BASE_DELAY
+          .saturating_mul(2u32.saturating_pow(restarts.min(8) - 1))
+          .min(RESTART_MAX_DELAY);
+        eprintln!(
+          "AppSync worker exited with an error: {}. Restarting in {:.2} seconds.",
+          e,
+          delay.as_secs_f64()
+        );
+        emit_state(
+          &app_handle,
+          ConnectionState::Restarting {
+            error: e.to_string(),
+            restarts,
+            restart_in_ms: delay.as_millis() as u64,
+          },
+        );
+        last_error = Some(e.to_string());
+
+        if !worker.sleep_serving_control(delay).await {
+          last_error = None;
+          break;
+        }
+      }
+    }
+  }
+
+  println!("AppSync worker stopped.");
+  finish(&app_handle, generation, last_error);
+}
+
+// Frees the worker slot and reports the client as stopped, unless a newer
+// client has taken over.
+fn finish(app_handle: &AppHandle, generation: u64, last_error: Option<String>) {
+  let mut slot = worker_slot().lock().unwrap();
+  if slot.as_ref().map(|h| h.generation) == Some(generation) {
+    *slot = None;
+  }
+  drop(slot);
+
+  // A newer client may already be running; don't clobber its state.
+  if GENERATION.load(Ordering::SeqCst) == generation {
+    emit_state(app_handle, ConnectionState::Stopped { last_error });
+  }
+}
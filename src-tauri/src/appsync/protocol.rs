@@ -1,13 +1,20 @@
+use super::dispatch::{notify_update, record_event};
+use super::follows::register_new_broadcasters;
+use super::state::{emit_state, stream_ended, stream_started, ConnectionState};
 use super::subscriptions::{
-  generate_desired_subscriptions, manage_subscriptions,
+  generate_desired_subscriptions, manage_subscriptions, send_start, send_stop,
+  PendingStart, RetryState,
 };
-use super::util;
 use super::worker::{AppSyncWorker, WsWrite};
-use crate::notifications::send_notification;
+use crate::command::Broadcasters;
+use crate::token::refresh_and_store;
+use crate::twitch::fetch_streamer_snapshots;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::time::Duration;
 use tauri::Emitter;
+use tokio::time::Instant;
 
 #[derive(Deserialize, Debug)]
 struct IncomingMessage<'a> {
@@ -24,6 +31,23 @@ struct StreamerUpdateEvent {
   payload: Value,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct StreamerUpdate {
+  pub broadcaster_id: String,
+  pub broadcaster_name: Option<String>,
+  pub broadcaster_login: Option<String>,
+  pub category: Option<String>,
+  pub title: Option<String>,
+  pub is_live: Option<bool>,
+  #[serde(rename = "type")]
+  pub update_type: Option<String>,
+  // What the client knew before this update; never sent by the backend.
+  #[serde(skip)]
+  pub previous_title: Option<String>,
+  #[serde(skip)]
+  pub previous_category: Option<String>,
+}
+
 pub async fn handle_message(
   worker: &mut AppSyncWorker,
   write: &mut WsWrite,
@@ -38,12 +62,16 @@ pub async fn handle_message(
   };
 
   match msg.msg_type {
-    "connection_ack" => handle_connection_ack(worker, write).await?,
-    "ka" | "keepalive" => { /* Keepalive received, no action needed */ }
+    "connection_ack" => {
+      handle_connection_ack(worker, write, msg.payload).await?
+    }
+    "ka" | "keepalive" => {
+      // The worker already stamped `last_message_at`, nothing else to do.
+    }
     "start_ack" => handle_start_ack(worker, msg.id),
     "data" | "next" => handle_data(worker, msg.id, msg.payload),
     "error" | "connection_error" => {
-      return handle_error(worker, msg.payload).await
+      return handle_error(worker, msg.id, msg.payload).await
     }
     "complete" => handle_complete(worker, msg.id),
     _ => println!("Received unknown message type: {}", msg.msg_type),
@@ -54,17 +82,20 @@ pub async fn handle_message(
 
 pub async fn update_and_manage_subscriptions(
   worker: &mut AppSyncWorker,
-  write: &mut WsWrite,
+  write: Option<&mut WsWrite>,
   streamer_ids: Vec<String>,
 ) -> anyhow::Result<()> {
-  let desired_subs = generate_desired_subscriptions(&streamer_ids).await;
+  register_new_broadcasters(&worker.config, &mut worker.follows, &streamer_ids)
+    .await;
+  let desired_subs =
+    generate_desired_subscriptions(&streamer_ids, &worker.active_subscriptions);
 
-  if worker.is_connected {
+  if let (true, Some(write)) = (worker.is_connected, write) {
     let token = worker.token.read().await.clone();
     manage_subscriptions(
       write,
       &token,
-      &worker.http_uri,
+      &worker.config.appsync_http_uri,
       &worker.active_subscriptions,
       &desired_subs,
       &mut worker.pending_subscriptions,
@@ -72,37 +103,262 @@ pub async fn update_and_manage_subscriptions(
     .await?;
   }
 
+  worker
+    .subscription_retries
+    .retain(|broadcaster_id, _| desired_subs.contains_key(broadcaster_id));
   worker.active_subscriptions = desired_subs;
+  if worker.is_connected {
+    worker.report_subscription_progress();
+  }
   Ok(())
 }
 
 async fn handle_connection_ack(
   worker: &mut AppSyncWorker,
   write: &mut WsWrite,
+  payload: Option<Value>,
 ) -> anyhow::Result<()> {
   println!("Connection acknowledged by server.");
   worker.is_connected = true;
+  worker.connected_at = Some(Instant::now());
+  worker.was_acked = true;
+
+  if let Some(timeout_ms) = payload
+    .as_ref()
+    .and_then(|p| p.get("connectionTimeoutMs"))
+    .and_then(Value::as_u64)
+  {
+    worker.connection_timeout = Duration::from_millis(timeout_ms);
+  }
+  emit_state(
+    &worker.app_handle,
+    ConnectionState::Acked {
+      connection_timeout_ms: worker.connection_timeout.as_millis() as u64,
+    },
+  );
 
   let token = worker.token.read().await.clone();
   let desired_subs = worker.active_subscriptions.clone();
+  worker.pending_subscriptions.clear();
+  worker.subscription_retries.clear();
 
   manage_subscriptions(
     write,
     &token,
-    &worker.http_uri,
+    &worker.config.appsync_http_uri,
     &HashMap::new(),
     &desired_subs,
     &mut worker.pending_subscriptions,
   )
   .await?;
+  worker.report_subscription_progress();
+
+  reconcile_missed_events(worker).await;
+
+  Ok(())
+}
+
+// Events published while we were disconnected are gone, so compare a fresh
+// snapshot from the backend with what we last knew and replay the difference.
+async fn reconcile_missed_events(worker: &mut AppSyncWorker) {
+  let broadcaster_ids: Vec<String> =
+    worker.active_subscriptions.keys().cloned().collect();
+  if broadcaster_ids.is_empty() {
+    return;
+  }
+
+  let snapshots =
+    match fetch_streamer_snapshots(&worker.config, &broadcaster_ids).await {
+      Ok(s) => s,
+      Err(e) => {
+        eprintln!("Failed to fetch snapshot after reconnect: {}", e);
+        return;
+      }
+    };
+
+  let has_snapshot = worker.has_snapshot;
+  worker.has_snapshot = true;
+
+  for current in snapshots {
+    let previous = worker
+      .known_streamers
+      .insert(current.broadcaster_id.clone(), current.clone());
+
+    // Streams that were already live when we first see them count from now,
+    // which is the best we know.
+    if current.is_live {
+      stream_started(&worker.app_handle, &current.broadcaster_id);
+    }
+
+    let previous = match previous {
+      Some(p) if has_snapshot => p,
+      _ => {
+        // Nothing to replay, but a stream that ended while we weren't
+        // watching shouldn't keep its start time.
+        if !current.is_live {
+          stream_ended(&worker.app_handle, &current.broadcaster_id);
+        }
+        continue;
+      }
+    };
+
+    let update_type = if previous.is_live != current.is_live {
+      if current.is_live {
+        "status"
+      } else {
+        "offline"
+      }
+    } else if previous.title != current.title
+      || previous.category != current.category
+    {
+      "channel_updated"
+    } else {
+      continue;
+    };
+
+    println!(
+      "Reconciled missed '{}' event for {}.",
+      update_type, current.broadcaster_name
+    );
+
+    let event_payload = StreamerUpdateEvent {
+      sub_id: None,
+      broadcaster_id: Some(current.broadcaster_id.clone()),
+      payload: json!({
+        "broadcaster_id": &current.broadcaster_id,
+        "broadcaster_name": &current.broadcaster_name,
+        "broadcaster_login": &current.broadcaster_login,
+        "category": &current.category,
+        "title": &current.title,
+        "is_live": current.is_live,
+        "type": update_type,
+      }),
+    };
+    if let Err(e) = worker.app_handle.emit("streamer:update", event_payload) {
+      eprintln!("Error emitting 'streamer:update' event: {}", e);
+    }
+
+    let update = StreamerUpdate {
+      broadcaster_id: current.broadcaster_id,
+      broadcaster_name: Some(current.broadcaster_name),
+      broadcaster_login: Some(current.broadcaster_login),
+      category: Some(current.category),
+      title: Some(current.title),
+      is_live: Some(current.is_live),
+      update_type: Some(update_type.to_string()),
+      previous_title: Some(previous.title),
+      previous_category: Some(previous.category),
+    };
+    record_event(worker, &update);
+    // Reconciled title edits are old news; go-lives and stream ends aren't.
+    if update_type != "channel_updated" {
+      notify_update(worker, &update);
+    }
+  }
+}
+
+fn remember_update(worker: &mut AppSyncWorker, update: &mut StreamerUpdate) {
+  let known = worker
+    .known_streamers
+    .entry(update.broadcaster_id.clone())
+    .or_insert_with(|| Broadcasters {
+      broadcaster_id: update.broadcaster_id.clone(),
+      broadcaster_name: String::new(),
+      broadcaster_login: String::new(),
+      category: String::new(),
+      title: String::new(),
+      is_live: false,
+      profile_picture: None,
+    });
+  update.previous_title = Some(known.title.clone());
+  update.previous_category = Some(known.category.clone());
+
+  if let Some(name) = &update.broadcaster_name {
+    known.broadcaster_name = name.clone();
+  }
+  if let Some(login) =
+    update.broadcaster_login.as_ref().filter(|l| !l.is_empty())
+  {
+    known.broadcaster_login = login.clone();
+  }
+  if let Some(category) = &update.category {
+    known.category = category.clone();
+  }
+  if let Some(title) = &update.title {
+    known.title = title.clone();
+  }
+  if let Some(is_live) = update.is_live {
+    known.is_live = is_live;
+  }
+}
+
+pub async fn check_subscription_deadlines(
+  worker: &mut AppSyncWorker,
+  write: &mut WsWrite,
+) -> anyhow::Result<()> {
+  let now = Instant::now();
+
+  let expired: Vec<(String, PendingStart)> = worker
+    .pending_subscriptions
+    .iter()
+    .filter(|(_, pending)| pending.deadline <= now)
+    .map(|(sub_id, pending)| (sub_id.clone(), pending.clone()))
+    .collect();
+
+  for (sub_id, pending) in &expired {
+    eprintln!(
+      "Subscription {} for {} was not acknowledged in time.",
+      sub_id, pending.broadcaster_id
+    );
+    worker.pending_subscriptions.remove(sub_id);
+    send_stop(write, sub_id).await?;
+    worker.subscription_retries.insert(
+      pending.broadcaster_id.clone(),
+      RetryState::after(pending, "start_ack timed out".to_string()),
+    );
+  }
+
+  let due: Vec<String> = worker
+    .subscription_retries
+    .iter()
+    .filter(|(_, retry)| retry.retry_at <= now)
+    .map(|(broadcaster_id, _)| broadcaster_id.clone())
+    .collect();
+
+  if expired.is_empty() && due.is_empty() {
+    return Ok(());
+  }
+
+  let token = worker.token.read().await.clone();
+  for broadcaster_id in due {
+    let Some(retry) = worker.subscription_retries.remove(&broadcaster_id)
+    else {
+      continue;
+    };
+    let Some(sub) = worker.active_subscriptions.get_mut(&broadcaster_id) else {
+      continue;
+    };
+
+    println!(
+      "Retrying subscription for {} (attempt {}).",
+      broadcaster_id, retry.attempt
+    );
+    sub.renew_id();
+    send_start(write, &token, &worker.config.appsync_http_uri, sub).await?;
+    worker
+      .pending_subscriptions
+      .insert(sub.id.clone(), PendingStart::retry(broadcaster_id, retry));
+  }
 
+  worker.report_subscription_progress();
   Ok(())
 }
 
 fn handle_start_ack(worker: &mut AppSyncWorker, id: Option<&str>) {
   if let Some(id_str) = id {
-    if worker.pending_subscriptions.remove(id_str) {
+    if worker.pending_subscriptions.remove(id_str).is_some() {
       println!("Subscription acknowledged: {}", id_str);
+      worker.report_subscription_progress();
     }
   }
 }
@@ -133,59 +389,62 @@ fn handle_data(
     eprintln!("Error emitting 'streamer:update' event: {}", e);
   }
 
-  if let (Some(name), Some(update_type)) = (
-    streamer_obj.get("broadcaster_name").and_then(Value::as_str),
-    streamer_obj.get("type").and_then(Value::as_str),
-  ) {
-    let title = streamer_obj
-      .get("title")
-      .and_then(Value::as_str)
-      .unwrap_or("");
-    let category = streamer_obj
-      .get("category")
-      .and_then(Value::as_str)
-      .unwrap_or("");
-    let msg = format!("{} - {}", category, title);
-
-    let heading = match update_type {
-      "channel_updated" => format!("{} - Channel updated", name),
-      "status" => format!("{} just went live!", name),
-      _ => return,
-    };
+  let mut update: StreamerUpdate = match serde_json::from_value(streamer_obj) {
+    Ok(u) => u,
+    Err(e) => {
+      eprintln!("Failed to parse streamer update: {}", e);
+      return;
+    }
+  };
 
-    let _ = send_notification(
-      heading,
-      msg,
-      name.to_string(),
-      worker.app_handle.clone(),
-    );
-  }
+  remember_update(worker, &mut update);
+  record_event(worker, &update);
+  notify_update(worker, &update);
 }
 
 async fn handle_error(
   worker: &mut AppSyncWorker,
+  id: Option<&str>,
   payload: Option<Value>,
 ) -> anyhow::Result<bool> {
   eprintln!("Received error from server: {:?}", payload);
-  let is_auth_error = payload
+  let error_message = payload
     .as_ref()
     .and_then(|p| p.get("errors"))
     .and_then(|e| e.as_array()?.get(0)?.get("message")?.as_str())
+    .map(String::from);
+  let is_auth_error = error_message
+    .as_ref()
     .map(|s| s.to_lowercase().contains("unauthor"))
     .unwrap_or(false);
 
+  // A failed start only affects that broadcaster, so retry it on its own
+  // instead of tearing down the whole connection.
+  if !is_auth_error {
+    if let Some(pending) =
+      id.and_then(|id_str| worker.pending_subscriptions.remove(id_str))
+    {
+      let error =
+        error_message.unwrap_or_else(|| "subscription failed".to_string());
+      eprintln!(
+        "Subscription for {} failed: {}. Scheduling retry.",
+        pending.broadcaster_id, error
+      );
+      worker.subscription_retries.insert(
+        pending.broadcaster_id.clone(),
+        RetryState::after(&pending, error),
+      );
+      worker.report_subscription_progress();
+      return Ok(true);
+    }
+  }
+
   if is_auth_error {
     println!("Authorization error detected. Attempting to refresh token.");
-    if let Ok(Some(refresh_token)) =
-      util::load_secret_blocking("refresh_token".to_string()).await
-    {
-      if let Ok(new_token) =
-        util::refresh_access_token_blocking(refresh_token).await
-      {
-        println!("Token refreshed successfully.");
-        *worker.token.write().await = new_token;
-        return Ok(false);
-      }
+    emit_state(&worker.app_handle, ConnectionState::Unauthorized);
+    if refresh_and_store(&worker.app_handle).await.is_ok() {
+      println!("Token refreshed successfully.");
+      return Ok(false);
     }
   }
 
@@ -198,3 +457,148 @@ fn handle_complete(worker: &mut AppSyncWorker, id: Option<&str>) {
     worker.pending_subscriptions.remove(id_str);
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::appsync::LiveSince;
+  use crate::notifications::{
+    NotificationBackend, NotificationRequest, Notifier, RecordingBackend,
+  };
+  use crate::settings::Settings;
+  use std::sync::{Arc, Mutex};
+  use tauri::test::{mock_app, MockRuntime};
+  use tauri::{App, Manager};
+
+  const ID: &str = "1234";
+
+  // The app has to outlive the worker's handle, so it's handed back too.
+  fn setup(
+    settings: Settings,
+  ) -> (App<MockRuntime>, AppSyncWorker, Arc<RecordingBackend>) {
+    let app = mock_app();
+    let recorder = Arc::new(RecordingBackend::default());
+    let backend: Arc<dyn NotificationBackend> = recorder.clone();
+    app.manage(Notifier::new(vec![backend]));
+    app.manage(Mutex::new(settings));
+    app.manage(Mutex::new(LiveSince::default()));
+    let worker = AppSyncWorker::for_tests(app.handle().clone());
+    (app, worker, recorder)
+  }
+
+  fn send(worker: &mut AppSyncWorker, update: Value) {
+    let payload = json!({ "data": { "onUpdateStreamer": update } });
+    handle_data(worker, Some("sub-1"), Some(payload));
+  }
+
+  fn update(update_type: &str, category: Option<&str>) -> Value {
+    json!({
+      "broadcaster_id": ID,
+      "broadcaster_name": "Streamer",
+      "broadcaster_login": "streamer",
+      "category": category,
+      "title": "Saturday hangout",
+      "is_live": update_type != "offline",
+      "type": update_type,
+    })
+  }
+
+  // Delivery runs on the async runtime, so wait for it to catch up.
+  fn delivered(
+    recorder: &RecordingBackend,
+    count: usize,
+  ) -> Vec<NotificationRequest> {
+    for _ in 0..200 {
+      if recorder.delivered().len() >= count {
+        break;
+      }
+      std::thread::sleep(Duration::from_millis(10));
+    }
+    recorder.delivered()
+  }
+
+  fn request(title: &str, body: &str) -> NotificationRequest {
+    NotificationRequest {
+      title: title.into(),
+      body: body.into(),
+      broadcaster_id: ID.into(),
+      broadcaster_login: "streamer".into(),
+      broadcaster_name: "Streamer".into(),
+      avatar: None,
+      thumbnail: None,
+      priority: false,
+    }
+  }
+
+  #[test]
+  fn status_notifies_go_live() {
+    let (_app, mut worker, recorder) = setup(Settings::default());
+
+    send(&mut worker, update("status", Some("Just Chatting")));
+
+    assert_eq!(
+      delivered(&recorder, 1),
+      vec![request(
+        "Streamer just went live!",
+        "Just Chatting - Saturday hangout"
+      )]
+    );
+  }
+
+  #[test]
+  fn channel_updated_notifies_what_changed() {
+    let (_app, mut worker, recorder) = setup(Settings {
+      channel_update_window_secs: 0,
+      ..Settings::default()
+    });
+
+    send(&mut worker, update("status", Some("Just Chatting")));
+    send(&mut worker, update("channel_updated", Some("Music")));
+    // Nothing changed this time.
+    send(&mut worker, update("channel_updated", Some("Music")));
+
+    let delivered = delivered(&recorder, 2);
+    assert_eq!(delivered.len(), 2);
+    assert_eq!(
+      delivered[1],
+      request(
+        "Streamer - Channel updated",
+        "Category: Just Chatting → Music"
+      )
+    );
+  }
+
+  #[test]
+  fn channel_updated_waits_for_the_merge_window() {
+    let (_app, mut worker, recorder) = setup(Settings::default());
+
+    send(&mut worker, update("channel_updated", Some("Music")));
+
+    assert!(recorder.delivered().is_empty());
+    assert!(worker.throttle.cancel_channel_update(ID).is_some());
+  }
+
+  #[test]
+  fn offline_notifies_only_when_opted_in() {
+    let (_app, mut worker, recorder) = setup(Settings::default());
+    send(&mut worker, update("offline", None));
+    assert!(recorder.delivered().is_empty());
+
+    let (_app, mut worker, recorder) = setup(Settings {
+      offline_notifications: vec![ID.into()],
+      ..Settings::default()
+    });
+    send(&mut worker, update("status", Some("Just Chatting")));
+    send(&mut worker, update("offline", None));
+
+    let delivered = delivered(&recorder, 2);
+    assert_eq!(delivered.len(), 2);
+    assert_eq!(
+      delivered[1],
+      request(
+        "Streamer went offline",
+        "Streamed for 0m - Last category: Just Chatting"
+      )
+    );
+  }
+}
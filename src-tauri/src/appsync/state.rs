@@ -0,0 +1,128 @@
+use super::subscriptions::SubscriptionHealth;
+use crate::AppHandle;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ConnectionState {
+  Connecting {
+    attempt: u32,
+  },
+  Acked {
+    connection_timeout_ms: u64,
+  },
+  Subscribed {
+    active: usize,
+    total: usize,
+  },
+  BackingOff {
+    attempt: u32,
+    delay_ms: u64,
+    retry_at_ms: u64,
+  },
+  KeepaliveTimeout {
+    silent_for_ms: u64,
+  },
+  Unauthorized,
+  Restarting {
+    error: String,
+    restarts: u32,
+    restart_in_ms: u64,
+  },
+  Stopped {
+    last_error: Option<String>,
+  },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WorkerStatus {
+  pub connected: bool,
+  pub paused: bool,
+  pub active_subscriptions: usize,
+  pub pending_subscriptions: usize,
+  pub connection_age_ms: Option<u64>,
+}
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static CURRENT_STATE: OnceLock<Mutex<ConnectionState>> = OnceLock::new();
+static SUBSCRIPTION_HEALTH: OnceLock<Mutex<Vec<SubscriptionHealth>>> =
+  OnceLock::new();
+
+fn current_state() -> &'static Mutex<ConnectionState> {
+  CURRENT_STATE
+    .get_or_init(|| Mutex::new(ConnectionState::Stopped { last_error: None }))
+}
+
+pub fn connection_state() -> ConnectionState {
+  current_state().lock().unwrap().clone()
+}
+
+pub fn emit_state(app_handle: &AppHandle, state: ConnectionState) {
+  *current_state().lock().unwrap() = state.clone();
+
+  if let Err(e) = app_handle.emit("connection:state", state) {
+    eprintln!("Error emitting 'connection:state' event: {}", e);
+  }
+}
+
+fn health_cell() -> &'static Mutex<Vec<SubscriptionHealth>> {
+  SUBSCRIPTION_HEALTH.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+pub fn subscription_health() -> Vec<SubscriptionHealth> {
+  health_cell().lock().unwrap().clone()
+}
+
+pub fn set_subscription_health(health: Vec<SubscriptionHealth>) {
+  *health_cell().lock().unwrap() = health;
+}
+
+pub fn is_paused() -> bool {
+  PAUSED.load(Ordering::SeqCst)
+}
+
+pub fn set_paused(app_handle: &AppHandle, paused: bool) {
+  PAUSED.store(paused, Ordering::SeqCst);
+
+  if let Err(e) = app_handle.emit("notifications:paused", paused) {
+    eprintln!("Error emitting 'notifications:paused' event: {}", e);
+  }
+}
+
+// When each stream went live, by broadcaster id. It's app state rather than
+// the worker's, so worker restarts and re-logins don't lose it.
+#[derive(Default)]
+pub struct LiveSince(HashMap<String, Instant>);
+
+// Keeps the earlier start when the stream was already known to be live.
+pub fn stream_started(app: &AppHandle, broadcaster_id: &str) {
+  if let Some(live) = app.try_state::<Mutex<LiveSince>>() {
+    live
+      .lock()
+      .unwrap()
+      .0
+      .entry(broadcaster_id.to_string())
+      .or_insert_with(Instant::now);
+  }
+}
+
+// How long the stream ran, if we saw it start.
+pub fn stream_ended(app: &AppHandle, broadcaster_id: &str) -> Option<Duration> {
+  let live = app.try_state::<Mutex<LiveSince>>()?;
+  let started = live.lock().unwrap().0.remove(broadcaster_id)?;
+  Some(started.elapsed())
+}
+
+pub fn stream_uptime(
+  app: &AppHandle,
+  broadcaster_id: &str,
+) -> Option<Duration> {
+  let live = app.try_state::<Mutex<LiveSince>>()?;
+  let started = *live.lock().unwrap().0.get(broadcaster_id)?;
+  Some(started.elapsed())
+}
@@ -1,22 +1,105 @@
 use super::worker::WsWrite;
-use crate::twitch::{register_streamers_webhook, Broadcaster};
 use futures_util::SinkExt;
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::time::Instant;
 use tokio_tungstenite::tungstenite::protocol::Message;
 use uuid::Uuid;
 
+const START_ACK_TIMEOUT: Duration = Duration::from_secs(15);
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(300);
+
 #[derive(Debug, Clone)]
 pub struct ActiveSubscription {
+  pub id: String,
   pub query: String,
   pub variables: Value,
 }
 
+impl ActiveSubscription {
+  // AppSync ids are per connection, so a retried start gets a fresh one in
+  // case the original start is still lingering server side.
+  pub fn renew_id(&mut self) {
+    self.id = Uuid::new_v4().to_string();
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingStart {
+  pub broadcaster_id: String,
+  pub deadline: Instant,
+  pub attempt: u32,
+  pub last_error: Option<String>,
+}
+
+impl PendingStart {
+  pub fn new(broadcaster_id: String) -> Self {
+    Self {
+      broadcaster_id,
+      deadline: Instant::now() + START_ACK_TIMEOUT,
+      attempt: 0,
+      last_error: None,
+    }
+  }
+
+  pub fn retry(broadcaster_id: String, retry: RetryState) -> Self {
+    Self {
+      broadcaster_id,
+      deadline: Instant::now() + START_ACK_TIMEOUT,
+      attempt: retry.attempt,
+      last_error: Some(retry.last_error),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryState {
+  pub attempt: u32,
+  pub retry_at: Instant,
+  pub last_error: String,
+}
+
+impl RetryState {
+  pub fn after(pending: &PendingStart, error: String) -> Self {
+    let attempt = pending.attempt.saturating_add(1);
+    let delay = RETRY_BASE_DELAY
+      .saturating_mul(2u32.saturating_pow(attempt.min(8) - 1))
+      .min(RETRY_MAX_DELAY);
+    Self {
+      attempt,
+      retry_at: Instant::now() + delay,
+      last_error: error,
+    }
+  }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+  Active,
+  Pending,
+  Retrying,
+  Disconnected,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SubscriptionHealth {
+  pub broadcaster_id: String,
+  pub status: SubscriptionStatus,
+  pub attempt: u32,
+  pub next_retry_in_ms: Option<u64>,
+  pub last_error: Option<String>,
+}
+
 fn subscription_query() -> String {
   r#"subscription OnUpdateStreamer($broadcaster_id: String!) {
         onUpdateStreamer(broadcaster_id: $broadcaster_id) {
             broadcaster_id
             broadcaster_name
+            broadcaster_login
             category
             title
             is_live
@@ -26,80 +109,92 @@ fn subscription_query() -> String {
     .to_string()
 }
 
-pub async fn generate_desired_subscriptions(
+// Subscriptions are keyed by broadcaster id. Broadcasters that already have a
+// subscription keep it (and its id), so reloads only touch actual changes.
+pub fn generate_desired_subscriptions(
   streamer_ids: &[String],
+  current_subs: &HashMap<String, ActiveSubscription>,
 ) -> HashMap<String, ActiveSubscription> {
-  let broadcasters: Vec<Broadcaster> = streamer_ids
-    .iter()
-    .filter_map(|id| match id.parse::<u64>() {
-      Ok(parsed_id) => Some(Broadcaster {
-        broadcaster_id: parsed_id,
-      }),
-      Err(e) => {
-        eprintln!("Skipping invalid broadcaster id '{}': {}", id, e);
-        None
-      }
-    })
-    .collect();
-
-  if !broadcasters.is_empty() {
-    register_streamers_webhook(broadcasters).await;
-  }
-
   streamer_ids
     .iter()
     .map(|bid| {
+      if let Some(existing) = current_subs.get(bid) {
+        return (bid.clone(), existing.clone());
+      }
+
       let uuid = Uuid::new_v4().to_string();
       let query = subscription_query();
       let vars = json!({ "broadcaster_id": bid.clone() });
       let sub = ActiveSubscription {
+        id: uuid,
         query,
         variables: vars,
       };
-      (uuid, sub)
+      (bid.clone(), sub)
     })
     .collect()
 }
 
+pub async fn send_start(
+  write: &mut WsWrite,
+  token: &str,
+  http_uri: &str,
+  sub: &ActiveSubscription,
+) -> anyhow::Result<()> {
+  let start_msg = json!({
+      "id": &sub.id,
+      "type": "start",
+      "payload": {
+          "data": json!({
+              "query": &sub.query,
+              "variables": &sub.variables
+          }).to_string(),
+          "extensions": {
+              "authorization": {
+                  "Authorization": format!("Bearer {}", token),
+                  "host": http_uri
+              }
+          }
+      },
+  })
+  .to_string();
+
+  write.send(Message::Text(start_msg)).await?;
+  Ok(())
+}
+
+pub async fn send_stop(
+  write: &mut WsWrite,
+  sub_id: &str,
+) -> anyhow::Result<()> {
+  let stop_msg = json!({ "id": sub_id, "type": "stop" }).to_string();
+  write.send(Message::Text(stop_msg)).await?;
+  Ok(())
+}
+
 pub async fn manage_subscriptions(
   write: &mut WsWrite,
   token: &str,
   http_uri: &str,
   current_subs: &HashMap<String, ActiveSubscription>,
   desired_subs: &HashMap<String, ActiveSubscription>,
-  pending_subscriptions: &mut HashSet<String>,
+  pending_subscriptions: &mut HashMap<String, PendingStart>,
 ) -> anyhow::Result<()> {
   let current_ids: HashSet<_> = current_subs.keys().collect();
   let desired_ids: HashSet<_> = desired_subs.keys().collect();
 
-  for sub_id in current_ids.difference(&desired_ids) {
-    let stop_msg = json!({ "id": *sub_id, "type": "stop" }).to_string();
-    write.send(Message::Text(stop_msg)).await?;
-    pending_subscriptions.remove(*sub_id);
+  for broadcaster_id in current_ids.difference(&desired_ids) {
+    if let Some(sub) = current_subs.get(*broadcaster_id) {
+      send_stop(write, &sub.id).await?;
+      pending_subscriptions.remove(&sub.id);
+    }
   }
 
-  for sub_id in desired_ids.difference(&current_ids) {
-    if let Some(sub) = desired_subs.get(*sub_id) {
-      let start_msg = json!({
-          "id": *sub_id,
-          "type": "start",
-          "payload": {
-              "data": json!({
-                  "query": &sub.query,
-                  "variables": &sub.variables
-              }).to_string(),
-              "extensions": {
-                  "authorization": {
-                      "Authorization": format!("Bearer {}", token),
-                      "host": http_uri
-                  }
-              }
-          },
-      })
-      .to_string();
-
-      write.send(Message::Text(start_msg)).await?;
-      pending_subscriptions.insert((*sub_id).clone());
+  for broadcaster_id in desired_ids.difference(&current_ids) {
+    if let Some(sub) = desired_subs.get(*broadcaster_id) {
+      send_start(write, token, http_uri, sub).await?;
+      pending_subscriptions
+        .insert(sub.id.clone(), PendingStart::new((*broadcaster_id).clone()));
     }
   }
 
@@ -0,0 +1,115 @@
+use super::protocol::StreamerUpdate;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// However busy a streamer is with edits, a merged update goes out after this
+// many windows.
+const MAX_MERGE_WINDOWS: u32 = 5;
+
+#[derive(Debug, Clone)]
+pub struct MergedChannelUpdate {
+  pub update: StreamerUpdate,
+  pub merged: u32,
+  first_seen: Instant,
+  due: Instant,
+}
+
+// Per-broadcaster notification state: when each one last went live, and the
+// channel updates still waiting out their merge window.
+#[derive(Default)]
+pub struct NotificationThrottle {
+  last_live: HashMap<String, Instant>,
+  channel_updates: HashMap<String, MergedChannelUpdate>,
+}
+
+impl NotificationThrottle {
+  // A go-live within `cooldown` of the previous one is a flapping stream, not
+  // a new broadcast. Every go-live restarts the cooldown.
+  pub fn allow_live(
+    &mut self,
+    broadcaster_id: &str,
+    cooldown: Duration,
+  ) -> bool {
+    let now = Instant::now();
+    let previous = self.last_live.insert(broadcaster_id.to_string(), now);
+    match previous {
+      Some(at) => now.duration_since(at) >= cooldown,
+      None => true,
+    }
+  }
+
+  // Drops a channel update still waiting out its window.
+  pub fn cancel_channel_update(
+    &mut self,
+    broadcaster_id: &str,
+  ) -> Option<MergedChannelUpdate> {
+    self.channel_updates.remove(broadcaster_id)
+  }
+
+  // Returns whether the update was merged into one already waiting.
+  pub fn queue_channel_update(
+    &mut self,
+    update: StreamerUpdate,
+    window: Duration,
+  ) -> bool {
+    let now = Instant::now();
+    match self.channel_updates.get_mut(&update.broadcaster_id) {
+      Some(pending) => {
+        let previous = &pending.update;
+        pending.update = StreamerUpdate {
+          broadcaster_name: update
+            .broadcaster_name
+            .or_else(|| previous.broadcaster_name.clone()),
+          broadcaster_login: update
+            .broadcaster_login
+            .or_else(|| previous.broadcaster_login.clone()),
+          category: update.category.or_else(|| previous.category.clone()),
+          title: update.title.or_else(|| previous.title.clone()),
+          // Compare against how the channel looked before the burst.
+          previous_title: previous.previous_title.clone(),
+          previous_category: previous.previous_category.clone(),
+          ..update
+        };
+        pending.merged += 1;
+        // Windows too long for an Instant are cut short, not overflowed.
+        let deadline = window
+          .checked_mul(MAX_MERGE_WINDOWS)
+          .and_then(|max| pending.first_seen.checked_add(max));
+        pending.due = [now.checked_add(window), deadline]
+          .into_iter()
+          .flatten()
+          .min()
+          .unwrap_or(now);
+        true
+      }
+      None => {
+        self.channel_updates.insert(
+          update.broadcaster_id.clone(),
+          MergedChannelUpdate {
+            update,
+            merged: 1,
+            first_seen: now,
+            due: now.checked_add(window).unwrap_or(now),
+          },
+        );
+        false
+      }
+    }
+  }
+
+  pub fn take_due_channel_updates(&mut self) -> Vec<MergedChannelUpdate> {
+    let now = Instant::now();
+    let due: Vec<String> = self
+      .channel_updates
+      .iter()
+      .filter(|(_, pending)| pending.due <= now)
+      .map(|(id, _)| id.clone())
+      .collect();
+
+    due
+      .into_iter()
+      .filter_map(|id| self.channel_updates.remove(&id))
+      .collect()
+  }
+}
@@ -1,37 +1,76 @@
-use super::protocol::{handle_message, update_and_manage_subscriptions};
-use super::subscriptions::{self, ActiveSubscription};
+use super::dispatch::flush_channel_updates;
+use super::follows::{
+  announce_follow_changes, register_new_broadcasters, FollowTracker,
+};
+use super::protocol::{
+  check_subscription_deadlines, handle_message, update_and_manage_subscriptions,
+};
+use super::state::{
+  emit_state, is_paused, set_paused, set_subscription_health, ConnectionState,
+  WorkerStatus,
+};
+use super::subscriptions::{
+  self, ActiveSubscription, PendingStart, RetryState, SubscriptionHealth,
+  SubscriptionStatus,
+};
+use super::throttle::NotificationThrottle;
 use super::util;
 use super::ControlMsg;
+use crate::command::Broadcasters;
+use crate::config::AppConfig;
+use crate::token::{refresh_and_store, SharedToken};
 use crate::twitch::fetch_followed_streamers;
+use crate::watchlist::merge_watched;
+use crate::AppHandle;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
-use dotenvy_macro::dotenv;
 use futures_util::{stream::SplitSink, SinkExt, StreamExt};
 use http::Request;
 use rand::Rng;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
-use tauri::AppHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Manager;
 use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::RwLock;
+use tokio::time::Instant;
 use tokio_tungstenite::tungstenite::handshake::client::generate_key;
 use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_tungstenite::tungstenite::Error as WsError;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use url::Url;
 
+// AppSync's documented default, used until `connection_ack` tells us otherwise.
+const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_millis(300_000);
+
 pub type WsWrite =
   SplitSink<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>, Message>;
 
+enum CtrlOutcome {
+  Continue,
+  Reconnect,
+  Stop,
+}
+
 pub struct AppSyncWorker {
   pub app_handle: AppHandle,
   pub ctrl_rx: UnboundedReceiver<ControlMsg>,
   pub token: Arc<RwLock<String>>,
-  pub http_uri: String,
-  realtime_uri: String,
+  pub config: AppConfig,
   user_id: String,
+  pub follows: FollowTracker,
   pub active_subscriptions: HashMap<String, ActiveSubscription>,
-  pub pending_subscriptions: HashSet<String>,
+  pub pending_subscriptions: HashMap<String, PendingStart>,
+  pub subscription_retries: HashMap<String, RetryState>,
   pub is_connected: bool,
+  pub connected_at: Option<Instant>,
+  // Set once a connection is acked; the supervisor resets its backoff on it.
+  pub was_acked: bool,
+  pub paused: bool,
+  pub throttle: NotificationThrottle,
+  pub connection_timeout: Duration,
+  pub last_message_at: Instant,
+  pub known_streamers: HashMap<String, Broadcasters>,
+  pub has_snapshot: bool,
 }
 
 impl AppSyncWorker {
@@ -40,51 +79,108 @@ impl AppSyncWorker {
     ctrl_rx: UnboundedReceiver<ControlMsg>,
     token: String,
   ) -> Self {
+    let config = app_handle.state::<AppConfig>().inner().clone();
     let user_id = util::load_secret_blocking("user_id".to_string())
       .await
       .ok()
       .flatten()
       .unwrap_or_default();
 
-    let initial_streamers = fetch_followed_streamers(&token, &user_id)
-      .await
-      .unwrap_or_else(|e| {
-        eprintln!("Failed to fetch initial streamers: {}", e);
-        Vec::new()
-      });
-
-    let active_subscriptions =
-      subscriptions::generate_desired_subscriptions(&initial_streamers).await;
+    // Leave the tracker without a baseline if this fails, so the first
+    // successful reload isn't mistaken for a batch of new follows.
+    let mut follows = FollowTracker::default();
+    match fetch_followed_streamers(&config, &token, &user_id).await {
+      Ok(followed_ids) => {
+        follows.update(followed_ids);
+      }
+      Err(e) => eprintln!("Failed to fetch initial streamers: {}", e),
+    }
+    let streamer_ids = merge_watched(&app_handle, follows.followed().to_vec());
+    register_new_broadcasters(&config, &mut follows, &streamer_ids).await;
+    let active_subscriptions = subscriptions::generate_desired_subscriptions(
+      &streamer_ids,
+      &HashMap::new(),
+    );
+    let token = app_handle.state::<SharedToken>().0.clone();
 
     Self {
       app_handle,
       ctrl_rx,
-      token: Arc::new(RwLock::new(token)),
-      http_uri: dotenv!("APPSYNC_HTTP_URI").to_string(),
-      realtime_uri: dotenv!("APPSYNC_REALTIME_URI").to_string(),
+      token,
+      config,
       user_id,
+      follows,
       active_subscriptions,
-      pending_subscriptions: HashSet::new(),
+      pending_subscriptions: HashMap::new(),
+      subscription_retries: HashMap::new(),
+      is_connected: false,
+      connected_at: None,
+      was_acked: false,
+      // Carry the pause over a logout/login so the tray stays truthful.
+      paused: is_paused(),
+      throttle: NotificationThrottle::default(),
+      connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+      last_message_at: Instant::now(),
+      known_streamers: HashMap::new(),
+      has_snapshot: false,
+    }
+  }
+
+  // A worker that never connects, for feeding messages to the handlers in
+  // tests.
+  #[cfg(test)]
+  pub fn for_tests(app_handle: AppHandle) -> Self {
+    let (_, ctrl_rx) = tokio::sync::mpsc::unbounded_channel();
+    Self {
+      app_handle,
+      ctrl_rx,
+      token: Arc::new(RwLock::new(String::new())),
+      config: AppConfig::default(),
+      user_id: String::new(),
+      follows: FollowTracker::default(),
+      active_subscriptions: HashMap::new(),
+      pending_subscriptions: HashMap::new(),
+      subscription_retries: HashMap::new(),
       is_connected: false,
+      connected_at: None,
+      was_acked: false,
+      paused: false,
+      throttle: NotificationThrottle::default(),
+      connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+      last_message_at: Instant::now(),
+      known_streamers: HashMap::new(),
+      has_snapshot: false,
     }
   }
 
-  pub async fn run(mut self) -> anyhow::Result<()> {
+  pub async fn run(&mut self) -> anyhow::Result<()> {
     println!("AppSync worker starting.");
     let mut backoff_attempt: u32 = 0;
     let mut reload_interval = tokio::time::interval(Duration::from_secs(180));
     reload_interval
       .set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut deadline_interval = tokio::time::interval(Duration::from_secs(1));
+    deadline_interval
+      .set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
     'reconnect_loop: loop {
       println!("Attempting to connect to AppSync...");
+      emit_state(
+        &self.app_handle,
+        ConnectionState::Connecting {
+          attempt: backoff_attempt + 1,
+        },
+      );
       match self.connect().await {
         Ok(ws_stream) => {
           println!("WebSocket connection established.");
           backoff_attempt = 0;
           self.is_connected = false;
+          self.connection_timeout = DEFAULT_CONNECTION_TIMEOUT;
+          self.last_message_at = Instant::now();
 
           let (mut write, mut read) = ws_stream.split();
+          let mut reconnect_now = false;
 
           let init_msg =
             serde_json::json!({ "type": "connection_init" }).to_string();
@@ -95,39 +191,64 @@ impl AppSyncWorker {
 
           'message_loop: loop {
             tokio::select! {
-                Some(msg) = self.ctrl_rx.recv() => {
-                    match msg {
-                        ControlMsg::Stop => {
-                            println!("Stop signal received. Shutting down worker.");
-                            let _ = write.send(Message::Close(None)).await;
-                            return Ok(());
-                        }
-                        ControlMsg::UpdateSubscriptions { streamer_ids } => {
-                            println!("Received request to update subscriptions.");
-                            if let Err(e) = update_and_manage_subscriptions(&mut self, &mut write, streamer_ids).await {
-                                eprintln!("Failed to update subscriptions: {}", e);
-                            }
+                msg = self.ctrl_rx.recv() => {
+                    let outcome = match msg {
+                        Some(msg) => self.handle_control(msg, Some(&mut write)).await,
+                        None => CtrlOutcome::Stop,
+                    };
+                    match outcome {
+                        CtrlOutcome::Continue => {}
+                        CtrlOutcome::Reconnect => {
+                            reconnect_now = true;
+                            break 'message_loop;
                         }
+                        CtrlOutcome::Stop => return Ok(()),
                     }
                 }
 
                 _ = reload_interval.tick() => {
                     println!("Periodically reloading followed streamers.");
                     let token = self.token.read().await.clone();
-                    match fetch_followed_streamers(&token, &self.user_id).await {
-                        Ok(streamer_ids) => {
-                            if let Err(e) = update_and_manage_subscriptions(&mut self, &mut write, streamer_ids).await {
+                    match fetch_followed_streamers(&self.config, &token, &self.user_id).await {
+                        Ok(followed_ids) => {
+                            let changes = self.follows.update(followed_ids);
+                            let streamer_ids = merge_watched(&self.app_handle, self.follows.followed().to_vec());
+                            if let Err(e) = update_and_manage_subscriptions(self, Some(&mut write), streamer_ids).await {
                                 eprintln!("Failed to update subscriptions after reload: {}", e);
                             }
+                            // New follows are registered by now, so the backend can describe them.
+                            announce_follow_changes(self, changes).await;
                         }
                         Err(e) => eprintln!("Failed to fetch followed streamers: {}", e),
                     }
                 }
 
+                _ = deadline_interval.tick() => {
+                    flush_channel_updates(self);
+                    if self.is_connected {
+                        if let Err(e) = check_subscription_deadlines(self, &mut write).await {
+                            eprintln!("Failed to retry subscriptions: {}", e);
+                        }
+                    }
+                }
+
+                _ = tokio::time::sleep_until(self.last_message_at + self.connection_timeout) => {
+                    let silent_for = self.last_message_at.elapsed();
+                    eprintln!(
+                        "No message from server in {:.2} seconds. Forcing reconnect.",
+                        silent_for.as_secs_f64()
+                    );
+                    emit_state(&self.app_handle, ConnectionState::KeepaliveTimeout {
+                        silent_for_ms: silent_for.as_millis() as u64,
+                    });
+                    break 'message_loop;
+                }
+
                 Some(msg_result) = read.next() => {
+                    self.last_message_at = Instant::now();
                     match msg_result {
                         Ok(Message::Text(text)) => {
-                            if !handle_message(&mut self, &mut write, &text).await? {
+                            if !handle_message(self, &mut write, &text).await? {
                                 break 'message_loop;
                             }
                         }
@@ -144,19 +265,23 @@ impl AppSyncWorker {
                 }
             }
           }
+
+          self.is_connected = false;
+          self.connected_at = None;
+          set_subscription_health(self.subscription_health());
+
+          if reconnect_now {
+            continue 'reconnect_loop;
+          }
         }
         Err(e) => {
           eprintln!("Connection failed: {}. Attempting to refresh token.", e);
+          if is_unauthorized(&e) {
+            emit_state(&self.app_handle, ConnectionState::Unauthorized);
+          }
           // Attempt to refresh token if connection fails, as it might be expired
-          if let Ok(Some(refresh_token)) =
-            util::load_secret_blocking("refresh_token".to_string()).await
-          {
-            if let Ok(new_token) =
-              util::refresh_access_token_blocking(refresh_token).await
-            {
-              *self.token.write().await = new_token;
-              println!("Refreshed token due to connection failure.");
-            }
+          if refresh_and_store(&self.app_handle).await.is_ok() {
+            println!("Refreshed token due to connection failure.");
           }
         }
       }
@@ -172,24 +297,199 @@ impl AppSyncWorker {
         "Reconnecting in {:.2} seconds...",
         backoff_duration.as_secs_f64()
       );
-      tokio::time::sleep(backoff_duration).await;
+      let retry_at = SystemTime::now() + backoff_duration;
+      emit_state(
+        &self.app_handle,
+        ConnectionState::BackingOff {
+          attempt: backoff_attempt,
+          delay_ms: backoff_duration.as_millis() as u64,
+          retry_at_ms: retry_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        },
+      );
+      if !self.sleep_serving_control(backoff_duration).await {
+        return Ok(());
+      }
+    }
+  }
+
+  async fn handle_control(
+    &mut self,
+    msg: ControlMsg,
+    write: Option<&mut WsWrite>,
+  ) -> CtrlOutcome {
+    match msg {
+      ControlMsg::Stop => {
+        println!("Stop signal received. Shutting down worker.");
+        if let Some(write) = write {
+          let _ = write.send(Message::Close(None)).await;
+        }
+        CtrlOutcome::Stop
+      }
+      ControlMsg::Pause => {
+        println!("Pausing notifications.");
+        self.paused = true;
+        set_paused(&self.app_handle, true);
+        CtrlOutcome::Continue
+      }
+      ControlMsg::Resume => {
+        println!("Resuming notifications.");
+        self.paused = false;
+        set_paused(&self.app_handle, false);
+        CtrlOutcome::Continue
+      }
+      ControlMsg::ForceReconnect => {
+        println!("Reconnect requested.");
+        if let Some(write) = write {
+          let _ = write.send(Message::Close(None)).await;
+        }
+        CtrlOutcome::Reconnect
+      }
+      ControlMsg::QueryStatus { reply } => {
+        let _ = reply.send(self.status());
+        CtrlOutcome::Continue
+      }
+      ControlMsg::RefreshSubscriptions => {
+        println!("Watch list changed, refreshing subscriptions.");
+        let streamer_ids =
+          merge_watched(&self.app_handle, self.follows.followed().to_vec());
+        if let Err(e) =
+          update_and_manage_subscriptions(self, write, streamer_ids).await
+        {
+          eprintln!("Failed to refresh subscriptions: {}", e);
+        }
+        CtrlOutcome::Continue
+      }
+      ControlMsg::UpdateSubscriptions { streamer_ids } => {
+        println!("Received request to update subscriptions.");
+        if let Err(e) =
+          update_and_manage_subscriptions(self, write, streamer_ids).await
+        {
+          eprintln!("Failed to update subscriptions: {}", e);
+        }
+        CtrlOutcome::Continue
+      }
+    }
+  }
+
+  // Waits out a backoff while still answering control messages, so a stop
+  // request never has to wait for the next connection attempt. Returns false
+  // if the worker should shut down.
+  pub async fn sleep_serving_control(&mut self, duration: Duration) -> bool {
+    let sleep = tokio::time::sleep(duration);
+    tokio::pin!(sleep);
+
+    loop {
+      tokio::select! {
+          _ = &mut sleep => return true,
+          msg = self.ctrl_rx.recv() => {
+              let outcome = match msg {
+                  Some(msg) => self.handle_control(msg, None).await,
+                  None => CtrlOutcome::Stop,
+              };
+              match outcome {
+                  CtrlOutcome::Continue => {}
+                  // Cut the backoff short and connect right away.
+                  CtrlOutcome::Reconnect => return true,
+                  CtrlOutcome::Stop => return false,
+              }
+          }
+      }
     }
   }
 
+  fn status(&self) -> WorkerStatus {
+    let total = self.active_subscriptions.len();
+    let waiting =
+      self.pending_subscriptions.len() + self.subscription_retries.len();
+
+    WorkerStatus {
+      connected: self.is_connected,
+      paused: self.paused,
+      active_subscriptions: total.saturating_sub(waiting),
+      pending_subscriptions: waiting,
+      connection_age_ms: self
+        .connected_at
+        .map(|at| at.elapsed().as_millis() as u64),
+    }
+  }
+
+  pub fn report_subscription_progress(&self) {
+    let total = self.active_subscriptions.len();
+    let waiting =
+      self.pending_subscriptions.len() + self.subscription_retries.len();
+    emit_state(
+      &self.app_handle,
+      ConnectionState::Subscribed {
+        active: total.saturating_sub(waiting),
+        total,
+      },
+    );
+    set_subscription_health(self.subscription_health());
+  }
+
+  fn subscription_health(&self) -> Vec<SubscriptionHealth> {
+    let now = Instant::now();
+    let pending_by_broadcaster: HashMap<&str, &PendingStart> = self
+      .pending_subscriptions
+      .values()
+      .map(|p| (p.broadcaster_id.as_str(), p))
+      .collect();
+
+    let mut health: Vec<SubscriptionHealth> = self
+      .active_subscriptions
+      .keys()
+      .map(|broadcaster_id| {
+        let retry = self.subscription_retries.get(broadcaster_id);
+        let pending = pending_by_broadcaster.get(broadcaster_id.as_str());
+
+        let status = if !self.is_connected {
+          SubscriptionStatus::Disconnected
+        } else if retry.is_some() {
+          SubscriptionStatus::Retrying
+        } else if pending.is_some() {
+          SubscriptionStatus::Pending
+        } else {
+          SubscriptionStatus::Active
+        };
+
+        SubscriptionHealth {
+          broadcaster_id: broadcaster_id.clone(),
+          status,
+          attempt: retry
+            .map(|r| r.attempt)
+            .or_else(|| pending.map(|p| p.attempt))
+            .unwrap_or(0),
+          next_retry_in_ms: retry.map(|r| {
+            r.retry_at.saturating_duration_since(now).as_millis() as u64
+          }),
+          last_error: retry
+            .map(|r| r.last_error.clone())
+            .or_else(|| pending.and_then(|p| p.last_error.clone())),
+        }
+      })
+      .collect();
+
+    health.sort_by(|a, b| a.broadcaster_id.cmp(&b.broadcaster_id));
+    health
+  }
+
   async fn connect(
     &self,
   ) -> anyhow::Result<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>>
   {
     let token = self.token.read().await;
     let header_json = serde_json::json!({
-        "host": self.http_uri,
+        "host": self.config.appsync_http_uri,
         "Authorization": format!("Bearer {}", *token)
     });
     let header_b64 = URL_SAFE_NO_PAD.encode(header_json.to_string().as_bytes());
     let header_sub = format!("header-{}", header_b64);
     let protocols_value = format!("graphql-ws,{}", header_sub);
 
-    let url_str = format!("wss://{}/graphql", self.realtime_uri);
+    let url_str = format!("wss://{}/graphql", self.config.appsync_realtime_uri);
     let url = Url::parse(&url_str)?;
     let host = url
       .host_str()
@@ -218,3 +518,12 @@ impl AppSyncWorker {
     Ok(ws_stream)
   }
 }
+
+fn is_unauthorized(err: &anyhow::Error) -> bool {
+  match err.downcast_ref::<WsError>() {
+    Some(WsError::Http(response)) => {
+      matches!(response.status().as_u16(), 401 | 403)
+    }
+    _ => false,
+  }
+}
@@ -1,30 +1,47 @@
+use chrono::{DateTime, Utc};
 use std::{
-  collections::HashSet,
-  sync::{mpsc::Sender, Arc, Mutex, OnceLock},
+  collections::HashMap,
+  sync::{mpsc::Sender, Arc, Mutex},
   thread::JoinHandle,
 };
 
 use crate::{
-  appsync::ControlMsg,
+  appsync::{
+    connection_state, pause_ws_client, query_ws_client, reconnect_ws_client,
+    refresh_ws_subscriptions, resume_ws_client, subscription_health,
+    ConnectionState, SubscriptionHealth, WorkerStatus,
+  },
+  config::AppConfig,
+  favorites::Favorites,
   handle_setup_user,
+  history::{refresh_unread, History, HistoryItem},
+  launch::{open_stream, Stream},
   oauth::{gen_b64_url, generate_pkce_pair},
-  twitch::fetch_followed_streamers,
+  quiet::{emit_do_not_disturb, set_do_not_disturb as apply_do_not_disturb},
+  rules::{Rule, RuleEvent, RuleSet, RuleVerdict},
+  settings::Settings,
+  snooze::Snoozes,
+  storage::{edit_persisted, Persist},
+  templates::{Template, TemplateContext, TemplatePreview, TemplateSet},
+  token::SharedToken,
+  twitch::{fetch_followed_streamers, fetch_streamer_snapshots, fetch_user},
   util::load_secret,
+  watchlist::{merge_watched, WatchList, WatchedChannel},
+  AppHandle,
 };
-use dotenvy_macro::dotenv;
-use once_cell::sync::OnceCell;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{Emitter, Manager};
 use tauri_plugin_opener::OpenerExt;
-use tokio::sync::mpsc::UnboundedSender;
 use url::Url;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Broadcasters {
   pub broadcaster_id: String,
   pub broadcaster_name: String,
+  // Rows stored before logins were kept don't have one yet.
+  #[serde(default)]
+  pub broadcaster_login: String,
   pub category: String,
   pub title: String,
   pub is_live: bool,
@@ -36,11 +53,6 @@ pub struct ServerCtl {
   pub handle: JoinHandle<()>,
 }
 
-static CTRL_SENDER: OnceCell<UnboundedSender<ControlMsg>> = OnceCell::new();
-
-static CURRENT_SUBSCRIPTIONS: OnceLock<Mutex<HashSet<String>>> =
-  OnceLock::new();
-
 #[tauri::command]
 pub fn shutdown_server(
   state: tauri::State<std::sync::Mutex<Option<ServerCtl>>>,
@@ -68,27 +80,24 @@ pub fn on_startup(
 }
 
 #[tauri::command]
-pub fn login(app: AppHandle) {
-  let client_id = dotenv!("CLIENT_ID");
-  let redirect_uri = dotenv!("REDIRECT_URI");
-  let scope = dotenv!("SCOPE");
-
+pub fn login(app: AppHandle, config: tauri::State<'_, AppConfig>) {
   let (pkce_challenge, pkce_verifier) = generate_pkce_pair();
   let csrf_state = gen_b64_url();
   let nonce = gen_b64_url();
 
   let verifier_arc = Arc::new(Mutex::new(Some(pkce_verifier)));
 
-  let mut auth_url = Url::parse("https://id.twitch.tv/oauth2/authorize")
-    .expect("valid base url");
+  let mut auth_url =
+    Url::parse(&format!("{}/oauth2/authorize", config.twitch_id_uri))
+      .expect("valid base url");
 
   auth_url
     .query_pairs_mut()
     .append_pair("force_verify", "true")
     .append_pair("response_type", "code")
-    .append_pair("client_id", &client_id)
-    .append_pair("redirect_uri", &redirect_uri)
-    .append_pair("scope", &scope)
+    .append_pair("client_id", &config.client_id)
+    .append_pair("redirect_uri", &config.redirect_uri)
+    .append_pair("scope", &config.scope)
     .append_pair("state", &csrf_state)
     .append_pair("code_challenge", &pkce_challenge)
     .append_pair("code_challenge_method", "S256")
@@ -102,51 +111,9 @@ pub fn login(app: AppHandle) {
   let _ = app.opener().open_url(url_string, None::<&str>);
 }
 
-#[tauri::command]
-pub fn add_subscription(broadcaster_id: String) -> Result<(), String> {
-  let sender = CTRL_SENDER
-    .get()
-    .ok_or_else(|| "client not running".to_string())?;
-
-  let current_subs =
-    CURRENT_SUBSCRIPTIONS.get_or_init(|| Mutex::new(HashSet::new()));
-
-  let mut subs = current_subs.lock().unwrap();
-  subs.insert(broadcaster_id.clone());
-
-  let streamer_ids: Vec<String> = subs.iter().cloned().collect();
-
-  sender
-    .send(ControlMsg::UpdateSubscriptions { streamer_ids })
-    .map_err(|e| format!("send error: {}", e))?;
-
-  Ok(())
-}
-
-#[tauri::command]
-pub fn remove_subscription(broadcaster_id: String) -> Result<(), String> {
-  let sender = CTRL_SENDER
-    .get()
-    .ok_or_else(|| "client not running".to_string())?;
-
-  let current_subs =
-    CURRENT_SUBSCRIPTIONS.get_or_init(|| Mutex::new(HashSet::new()));
-
-  let mut subs = current_subs.lock().unwrap();
-  subs.remove(&broadcaster_id);
-
-  let streamer_ids: Vec<String> = subs.iter().cloned().collect();
-
-  sender
-    .send(ControlMsg::UpdateSubscriptions { streamer_ids })
-    .map_err(|e| format!("send error: {}", e))?;
-
-  Ok(())
-}
-
 #[tauri::command]
 pub fn fetch_streamers(app: AppHandle) {
-  let base_uri = dotenv!("BASE_URI");
+  let config = app.state::<AppConfig>().inner().clone();
   let token = load_secret("access_token").unwrap_or_default();
   let user_id = load_secret("user_id").unwrap_or_default();
 
@@ -156,65 +123,435 @@ pub fn fetch_streamers(app: AppHandle) {
   }
 
   tauri::async_runtime::spawn(async move {
-    let broadcaster_ids = match fetch_followed_streamers(&token, &user_id).await
-    {
-      Ok(ids) => ids,
-      Err(e) => {
-        eprintln!("Failed to fetch followed streamers: {:?}", e);
-        return;
-      }
-    };
-
-    let client = Client::new();
-
-    let res = client
-      .post(format!("{}/streamers/fetch-all", base_uri))
-      .json(&broadcaster_ids)
-      .send()
-      .await;
+    let broadcaster_ids =
+      match fetch_followed_streamers(&config, &token, &user_id).await {
+        Ok(ids) => merge_watched(&app, ids),
+        Err(e) => {
+          eprintln!("Failed to fetch followed streamers: {:?}", e);
+          return;
+        }
+      };
 
-    let streamers = match res {
-      Ok(resp) => match resp.json::<Vec<Broadcasters>>().await {
+    let streamers =
+      match fetch_streamer_snapshots(&config, &broadcaster_ids).await {
         Ok(s) => s,
         Err(e) => {
-          eprintln!("Failed to deserialize JSON: {:?}", e);
+          eprintln!("Failed to fetch streamers: {:?}", e);
           return;
         }
-      },
-      Err(e) => {
-        eprintln!("Failed to fetch streamers: {:?}", e);
-        return;
-      }
+      };
+
+    let (mut favorites, streamers): (Vec<Broadcasters>, Vec<Broadcasters>) = {
+      let state = app.state::<Mutex<Favorites>>();
+      let favorite_ids = state.lock().unwrap();
+      streamers
+        .into_iter()
+        .partition(|b| favorite_ids.contains(&b.broadcaster_id))
     };
-
     let (mut live, mut offline): (Vec<Broadcasters>, Vec<Broadcasters>) =
       streamers.into_iter().partition(|b| b.is_live);
 
-    live.sort_by(|a, b| {
+    let by_name = |a: &Broadcasters, b: &Broadcasters| {
       a.broadcaster_name
         .to_lowercase()
         .cmp(&b.broadcaster_name.to_lowercase())
-    });
-    offline.sort_by(|a, b| {
-      a.broadcaster_name
-        .to_lowercase()
-        .cmp(&b.broadcaster_name.to_lowercase())
-    });
+    };
+    // Live favorites first, then the rest of them.
+    favorites.sort_by(|a, b| b.is_live.cmp(&a.is_live).then(by_name(a, b)));
+    live.sort_by(by_name);
+    offline.sort_by(by_name);
 
     app
       .emit(
         "streamers:fetched",
-        json!({"online": live, "offline": offline}),
+        json!({"favorites": favorites, "online": live, "offline": offline}),
       )
       .unwrap_or_else(|e| eprintln!("Failed to emit event: {:?}", e));
   });
 }
 
 #[tauri::command]
-pub fn open_broadcaster_url(app: AppHandle, broadcaster_name: String) {
+pub fn get_watch_list(
+  watch_list: tauri::State<'_, Mutex<WatchList>>,
+) -> Vec<WatchedChannel> {
+  watch_list.lock().unwrap().channels.clone()
+}
+
+#[tauri::command]
+pub async fn add_to_watch_list(
+  app: AppHandle,
+  channel: String,
+) -> Result<WatchedChannel, String> {
+  let channel = channel.trim().trim_start_matches('@').to_lowercase();
+  if channel.is_empty() {
+    return Err("Channel must not be empty.".into());
+  }
+
+  let config = app.state::<AppConfig>().inner().clone();
+  let token = app.state::<SharedToken>().0.read().await.clone();
+  let user = fetch_user(&config, &token, &channel)
+    .await?
+    .ok_or_else(|| format!("No Twitch channel named '{}'.", channel))?;
+
+  let watched = WatchedChannel {
+    broadcaster_id: user.id,
+    login: user.login,
+    display_name: user.display_name,
+  };
+
+  {
+    let state = app.state::<Mutex<WatchList>>();
+    let mut list = state.lock().unwrap();
+    if list.contains(&watched.broadcaster_id) {
+      return Err(format!(
+        "{} is already on the watch list.",
+        watched.display_name
+      ));
+    }
+    list.channels.push(watched.clone());
+    if let Err(e) = list.save(&app) {
+      list.channels.pop();
+      return Err(e);
+    }
+  }
+
+  // The worker picks the change up once it's running if it isn't yet.
+  let _ = refresh_ws_subscriptions();
+  fetch_streamers(app);
+  Ok(watched)
+}
+
+#[tauri::command]
+pub fn remove_from_watch_list(
+  app: AppHandle,
+  broadcaster_id: String,
+) -> Result<(), String> {
+  {
+    let state = app.state::<Mutex<WatchList>>();
+    let mut list = state.lock().unwrap();
+    if !list.contains(&broadcaster_id) {
+      return Err("Channel is not on the watch list.".into());
+    }
+    list.channels.retain(|c| c.broadcaster_id != broadcaster_id);
+    list.save(&app)?;
+  }
+
+  let _ = refresh_ws_subscriptions();
+  fetch_streamers(app);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_favorites(
+  favorites: tauri::State<'_, Mutex<Favorites>>,
+) -> Vec<String> {
+  favorites.lock().unwrap().broadcaster_ids.clone()
+}
+
+#[tauri::command]
+pub fn set_favorite(
+  app: AppHandle,
+  broadcaster_id: String,
+  favorite: bool,
+) -> Result<(), String> {
+  {
+    let state = app.state::<Mutex<Favorites>>();
+    let mut favorites = state.lock().unwrap();
+    if favorites.contains(&broadcaster_id) == favorite {
+      return Ok(());
+    }
+    if favorite {
+      favorites.broadcaster_ids.push(broadcaster_id);
+    } else {
+      favorites.broadcaster_ids.retain(|id| id != &broadcaster_id);
+    }
+    favorites.save(&app)?;
+  }
+
+  // Regroups the list in the window.
+  fetch_streamers(app);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_snoozed(app: AppHandle) -> HashMap<String, Option<DateTime<Utc>>> {
+  let state = app.state::<Mutex<Snoozes>>();
+  let mut snoozes = state.lock().unwrap();
+  if snoozes.prune() {
+    let _ = snoozes.save(&app);
+  }
+  snoozes.until.clone()
+}
+
+// Silences a streamer for `duration` minutes.
+#[tauri::command]
+pub fn snooze_streamer(
+  app: AppHandle,
+  broadcaster_id: String,
+  duration: u64,
+) -> Result<DateTime<Utc>, String> {
+  if duration == 0 {
+    return Err("Snooze duration must be at least a minute.".into());
+  }
+  let until = i64::try_from(duration)
+    .ok()
+    .and_then(chrono::Duration::try_minutes)
+    .and_then(|minutes| Utc::now().checked_add_signed(minutes))
+    .ok_or("Snooze duration is too long.")?;
+
+  set_snooze(&app, broadcaster_id, Some(Some(until)))?;
+  Ok(until)
+}
+
+// Silences a streamer until it's unmuted.
+#[tauri::command]
+pub fn mute_streamer(
+  app: AppHandle,
+  broadcaster_id: String,
+  muted: bool,
+) -> Result<(), String> {
+  // Unmuting leaves a timed snooze alone.
+  if !muted {
+    let state = app.state::<Mutex<Snoozes>>();
+    if !state.lock().unwrap().is_muted(&broadcaster_id) {
+      return Ok(());
+    }
+  }
+  set_snooze(&app, broadcaster_id, muted.then_some(None))
+}
+
+#[tauri::command]
+pub fn unsnooze_streamer(
+  app: AppHandle,
+  broadcaster_id: String,
+) -> Result<(), String> {
+  set_snooze(&app, broadcaster_id, None)
+}
+
+// `None` lifts the snooze, `Some(None)` mutes and `Some(Some(t))` snoozes
+// until `t`.
+fn set_snooze(
+  app: &AppHandle,
+  broadcaster_id: String,
+  until: Option<Option<DateTime<Utc>>>,
+) -> Result<(), String> {
+  let state = app.state::<Mutex<Snoozes>>();
+  let mut snoozes = state.lock().unwrap();
+  let previous = match until {
+    Some(until) => snoozes.until.insert(broadcaster_id.clone(), until),
+    None => snoozes.until.remove(&broadcaster_id),
+  };
+  if let Err(e) = snoozes.save(app) {
+    match previous {
+      Some(previous) => snoozes.until.insert(broadcaster_id, previous),
+      None => snoozes.until.remove(&broadcaster_id),
+    };
+    return Err(e);
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_rules(rules: tauri::State<'_, Mutex<RuleSet>>) -> Vec<Rule> {
+  rules.lock().unwrap().rules.clone()
+}
+
+#[tauri::command]
+pub fn add_rule(app: AppHandle, rule: Rule) -> Result<Rule, String> {
+  edit_persisted(&app, |rules: &mut RuleSet| rules.add(rule))
+}
+
+#[tauri::command]
+pub fn update_rule(app: AppHandle, rule: Rule) -> Result<Rule, String> {
+  edit_persisted(&app, |rules: &mut RuleSet| rules.update(rule))
+}
+
+#[tauri::command]
+pub fn delete_rule(app: AppHandle, id: String) -> Result<(), String> {
+  edit_persisted(&app, |rules: &mut RuleSet| rules.remove(&id))
+}
+
+#[tauri::command]
+pub fn test_rules(
+  rules: tauri::State<'_, Mutex<RuleSet>>,
+  event: RuleEvent,
+) -> RuleVerdict {
+  rules.lock().unwrap().evaluate(&event)
+}
+
+#[tauri::command]
+pub fn list_templates(
+  templates: tauri::State<'_, Mutex<TemplateSet>>,
+) -> Vec<Template> {
+  templates.lock().unwrap().templates.clone()
+}
+
+#[tauri::command]
+pub fn add_template(
+  app: AppHandle,
+  template: Template,
+) -> Result<Template, String> {
+  edit_persisted(&app, |templates: &mut TemplateSet| templates.add(template))
+}
+
+#[tauri::command]
+pub fn update_template(
+  app: AppHandle,
+  template: Template,
+) -> Result<Template, String> {
+  edit_persisted(&app, |templates: &mut TemplateSet| {
+    templates.update(template)
+  })
+}
+
+#[tauri::command]
+pub fn delete_template(app: AppHandle, id: String) -> Result<(), String> {
+  edit_persisted(&app, |templates: &mut TemplateSet| templates.remove(&id))
+}
+
+// Renders a template without saving it, with sample values unless the caller
+// passes its own.
+#[tauri::command]
+pub fn preview_template(
+  template: Template,
+  context: Option<TemplateContext>,
+) -> Result<TemplatePreview, String> {
+  template.validate()?;
+  Ok(template.render(&context.unwrap_or_else(TemplateContext::sample)))
+}
+
+#[tauri::command]
+pub fn get_settings(settings: tauri::State<'_, Mutex<Settings>>) -> Settings {
+  settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn update_settings(
+  app: AppHandle,
+  settings: Settings,
+) -> Result<Settings, String> {
+  settings.validate()?;
+  settings.save(&app)?;
+
+  let state = app.state::<Mutex<Settings>>();
+  let previous =
+    std::mem::replace(&mut *state.lock().unwrap(), settings.clone());
+  if previous.do_not_disturb != settings.do_not_disturb {
+    emit_do_not_disturb(&app, settings.do_not_disturb);
+  }
+  Ok(settings)
+}
+
+#[tauri::command]
+pub fn set_offline_notifications(
+  app: AppHandle,
+  broadcaster_id: String,
+  enabled: bool,
+) -> Result<(), String> {
+  edit_persisted(&app, |settings: &mut Settings| {
+    settings
+      .offline_notifications
+      .retain(|id| id != &broadcaster_id);
+    if enabled {
+      settings.offline_notifications.push(broadcaster_id);
+    }
+    Ok(())
+  })
+}
+
+#[tauri::command]
+pub fn set_do_not_disturb(app: AppHandle, enabled: bool) -> Result<(), String> {
+  apply_do_not_disturb(&app, enabled)
+}
+
+#[tauri::command]
+pub fn get_history(
+  history: tauri::State<'_, History>,
+  before_id: Option<i64>,
+  limit: Option<u32>,
+) -> Result<Vec<HistoryItem>, String> {
+  history.page(before_id, limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn mark_history_read(
+  app: AppHandle,
+  ids: Option<Vec<i64>>,
+) -> Result<(), String> {
+  app
+    .try_state::<History>()
+    .ok_or("History is unavailable.")?
+    .mark_read(ids.as_deref())
+    .map_err(|e| e.to_string())?;
+  refresh_unread(&app);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn clear_history(app: AppHandle) -> Result<(), String> {
+  app
+    .try_state::<History>()
+    .ok_or("History is unavailable.")?
+    .clear()
+    .map_err(|e| e.to_string())?;
+  refresh_unread(&app);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_unread_count(
+  history: tauri::State<'_, History>,
+) -> Result<u64, String> {
+  history.unread_count().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_connection_status() -> ConnectionState {
+  connection_state()
+}
+
+#[tauri::command]
+pub fn get_subscription_health() -> Vec<SubscriptionHealth> {
+  subscription_health()
+}
+
+#[tauri::command]
+pub fn pause_notifications() -> Result<(), String> {
+  pause_ws_client()
+}
+
+#[tauri::command]
+pub fn resume_notifications() -> Result<(), String> {
+  resume_ws_client()
+}
+
+#[tauri::command]
+pub fn force_reconnect() -> Result<(), String> {
+  reconnect_ws_client()
+}
+
+#[tauri::command]
+pub async fn get_worker_status() -> Result<WorkerStatus, String> {
+  query_ws_client().await
+}
+
+#[tauri::command]
+pub fn open_broadcaster_url(
+  app: AppHandle,
+  broadcaster_name: String,
+  broadcaster_id: Option<String>,
+  broadcaster_login: Option<String>,
+) {
   println!("Broadcaster: {:?}", broadcaster_name);
-  let _ = app.opener().open_url(
-    format!("https://twitch.tv/{}", broadcaster_name),
-    None::<&str>,
+  let login = broadcaster_login
+    .filter(|l| !l.is_empty())
+    .unwrap_or_else(|| broadcaster_name.to_lowercase());
+  open_stream(
+    &app,
+    &Stream {
+      broadcaster_id: broadcaster_id.as_deref().unwrap_or_default(),
+      login: &login,
+      name: &broadcaster_name,
+    },
   );
 }
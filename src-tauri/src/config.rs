@@ -0,0 +1,171 @@
+use crate::AppHandle;
+use dotenvy_macro::dotenv;
+use serde::Deserialize;
+use std::fs;
+use tauri::Manager;
+use url::Url;
+
+const CONFIG_FILE: &str = "config.json";
+const ENV_PREFIX: &str = "NOTISR_";
+
+#[derive(Debug, Clone, Default)]
+pub struct AppConfig {
+  pub client_id: String,
+  pub client_secret: String,
+  pub scope: String,
+  pub redirect_uri: String,
+  pub base_uri: String,
+  pub appsync_http_uri: String,
+  pub appsync_realtime_uri: String,
+  pub twitch_id_uri: String,
+  pub twitch_api_uri: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ConfigFile {
+  client_id: Option<String>,
+  client_secret: Option<String>,
+  scope: Option<String>,
+  redirect_uri: Option<String>,
+  base_uri: Option<String>,
+  appsync_http_uri: Option<String>,
+  appsync_realtime_uri: Option<String>,
+  twitch_id_uri: Option<String>,
+  twitch_api_uri: Option<String>,
+}
+
+// Environment variables win over the config file, which wins over the values
+// compiled in from `.env`.
+fn resolve(key: &str, file_value: Option<String>, compiled: &str) -> String {
+  std::env::var(format!("{}{}", ENV_PREFIX, key.to_uppercase()))
+    .ok()
+    .or(file_value)
+    .map(|v| v.trim().to_string())
+    .filter(|v| !v.is_empty())
+    .unwrap_or_else(|| compiled.to_string())
+}
+
+fn validate_url(key: &str, value: &str) -> anyhow::Result<()> {
+  let url = Url::parse(value).map_err(|e| {
+    anyhow::anyhow!("{} is not a valid URL '{}': {}", key, value, e)
+  })?;
+  if !matches!(url.scheme(), "http" | "https") {
+    return Err(anyhow::anyhow!(
+      "{} must be an http(s) URL, got '{}'",
+      key,
+      value
+    ));
+  }
+  Ok(())
+}
+
+// The AppSync endpoints are used as bare hosts (`wss://{host}/graphql`).
+fn validate_host(key: &str, value: &str) -> anyhow::Result<()> {
+  if value.contains("://") {
+    return Err(anyhow::anyhow!(
+      "{} must be a host name without scheme, got '{}'",
+      key,
+      value
+    ));
+  }
+  validate_url(key, &format!("https://{}", value))
+}
+
+impl AppConfig {
+  pub fn load(app: &AppHandle) -> anyhow::Result<Self> {
+    let file = match app.path().app_config_dir() {
+      Ok(dir) => {
+        let path = dir.join(CONFIG_FILE);
+        match fs::read_to_string(&path) {
+          Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+            anyhow::anyhow!("Invalid config file {}: {}", path.display(), e)
+          })?,
+          Err(_) => ConfigFile::default(),
+        }
+      }
+      Err(e) => {
+        eprintln!("Could not resolve app config dir: {}", e);
+        ConfigFile::default()
+      }
+    };
+
+    let config = Self {
+      client_id: resolve("client_id", file.client_id, dotenv!("CLIENT_ID")),
+      client_secret: resolve(
+        "client_secret",
+        file.client_secret,
+        dotenv!("CLIENT_SECRET"),
+      ),
+      scope: resolve("scope", file.scope, dotenv!("SCOPE")),
+      redirect_uri: resolve(
+        "redirect_uri",
+        file.redirect_uri,
+        dotenv!("REDIRECT_URI"),
+      ),
+      base_uri: resolve("base_uri", file.base_uri, dotenv!("BASE_URI"))
+        .trim_end_matches('/')
+        .to_string(),
+      appsync_http_uri: resolve(
+        "appsync_http_uri",
+        file.appsync_http_uri,
+        dotenv!("APPSYNC_HTTP_URI"),
+      ),
+      appsync_realtime_uri: resolve(
+        "appsync_realtime_uri",
+        file.appsync_realtime_uri,
+        dotenv!("APPSYNC_REALTIME_URI"),
+      ),
+      twitch_id_uri: resolve(
+        "twitch_id_uri",
+        file.twitch_id_uri,
+        "https://id.twitch.tv",
+      )
+      .trim_end_matches('/')
+      .to_string(),
+      twitch_api_uri: resolve(
+        "twitch_api_uri",
+        file.twitch_api_uri,
+        "https://api.twitch.tv",
+      )
+      .trim_end_matches('/')
+      .to_string(),
+    };
+
+    config.validate()?;
+    Ok(config)
+  }
+
+  // The address the OAuth callback server listens on, taken from
+  // `redirect_uri`. The server only answers at the root path.
+  pub fn callback_address(&self) -> anyhow::Result<String> {
+    let url = Url::parse(&self.redirect_uri)?;
+    let host = url
+      .host_str()
+      .ok_or_else(|| anyhow::anyhow!("redirect_uri must have a host"))?;
+    let port = url
+      .port_or_known_default()
+      .ok_or_else(|| anyhow::anyhow!("redirect_uri must have a port"))?;
+    if url.path() != "/" {
+      return Err(anyhow::anyhow!(
+        "redirect_uri must not have a path, got '{}'",
+        self.redirect_uri
+      ));
+    }
+    Ok(format!("{}:{}", host, port))
+  }
+
+  fn validate(&self) -> anyhow::Result<()> {
+    if self.client_id.is_empty() {
+      return Err(anyhow::anyhow!("client_id must not be empty"));
+    }
+    validate_url("redirect_uri", &self.redirect_uri)?;
+    self.callback_address()?;
+    validate_url("base_uri", &self.base_uri)?;
+    validate_url("twitch_id_uri", &self.twitch_id_uri)?;
+    validate_url("twitch_api_uri", &self.twitch_api_uri)?;
+    validate_host("appsync_http_uri", &self.appsync_http_uri)?;
+    validate_host("appsync_realtime_uri", &self.appsync_realtime_uri)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::storage::{load_json, save_json};
+use crate::AppHandle;
+
+const FAVORITES_FILE: &str = "favorites.json";
+
+// Streamers whose notifications skip rules and quiet time, and stay on
+// screen until dismissed.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Favorites {
+  pub broadcaster_ids: Vec<String>,
+}
+
+impl Favorites {
+  pub fn load(app: &AppHandle) -> Self {
+    load_json(app, FAVORITES_FILE)
+  }
+
+  pub fn save(&self, app: &AppHandle) -> Result<(), String> {
+    save_json(app, FAVORITES_FILE, self)
+  }
+
+  pub fn contains(&self, broadcaster_id: &str) -> bool {
+    self.broadcaster_ids.iter().any(|id| id == broadcaster_id)
+  }
+}
+
+pub fn is_favorite(app: &AppHandle, broadcaster_id: &str) -> bool {
+  app
+    .try_state::<Mutex<Favorites>>()
+    .is_some_and(|favorites| favorites.lock().unwrap().contains(broadcaster_id))
+}
@@ -0,0 +1,281 @@
+use crate::AppHandle;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager};
+
+const HISTORY_FILE: &str = "history.db";
+const RETENTION_DAYS: i64 = 30;
+const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const TRAY_ID: &str = "main";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryKind {
+  // A streamer event as it came in.
+  Event,
+  // What we did about it.
+  Notification,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+  Shown,
+  Suppressed,
+  Deferred,
+}
+
+impl HistoryKind {
+  fn as_str(&self) -> &'static str {
+    match self {
+      HistoryKind::Event => "event",
+      HistoryKind::Notification => "notification",
+    }
+  }
+}
+
+impl Outcome {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Outcome::Shown => "shown",
+      Outcome::Suppressed => "suppressed",
+      Outcome::Deferred => "deferred",
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+  pub kind: HistoryKind,
+  pub broadcaster_id: String,
+  pub broadcaster_name: String,
+  pub event_type: String,
+  pub category: String,
+  pub title: String,
+  pub outcome: Option<Outcome>,
+  pub reason: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HistoryItem {
+  pub id: i64,
+  pub created_at: i64,
+  pub kind: String,
+  pub broadcaster_id: String,
+  pub broadcaster_name: String,
+  pub event_type: String,
+  pub category: String,
+  pub title: String,
+  pub outcome: Option<String>,
+  pub reason: Option<String>,
+  pub read: bool,
+}
+
+pub struct History {
+  conn: Mutex<Connection>,
+  // Inserts run on their own thread, in the order they were recorded, so
+  // the async worker never waits on the disk.
+  writer: Sender<HistoryEntry>,
+}
+
+fn now_ms() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_millis() as i64
+}
+
+impl History {
+  pub fn open(app: &AppHandle) -> anyhow::Result<Self> {
+    let dir = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let conn = Connection::open(dir.join(HISTORY_FILE))?;
+
+    conn.execute_batch(
+      "CREATE TABLE IF NOT EXISTS history (
+         id INTEGER PRIMARY KEY AUTOINCREMENT,
+         created_at INTEGER NOT NULL,
+         kind TEXT NOT NULL,
+         broadcaster_id TEXT NOT NULL,
+         broadcaster_name TEXT NOT NULL,
+         event_type TEXT NOT NULL,
+         category TEXT NOT NULL,
+         title TEXT NOT NULL,
+         outcome TEXT,
+         reason TEXT,
+         read INTEGER NOT NULL DEFAULT 0
+       );
+       CREATE INDEX IF NOT EXISTS history_unread
+         ON history (kind, outcome, read);",
+    )?;
+
+    let cutoff = now_ms() - RETENTION_DAYS * 24 * 60 * 60 * 1000;
+    conn
+      .execute("DELETE FROM history WHERE created_at < ?1", params![cutoff])?;
+
+    let (writer, entries) = channel();
+    let app = app.clone();
+    std::thread::spawn(move || {
+      for entry in entries {
+        write(&app, entry);
+      }
+    });
+
+    Ok(Self {
+      conn: Mutex::new(conn),
+      writer,
+    })
+  }
+
+  fn insert(&self, entry: &HistoryEntry) -> rusqlite::Result<()> {
+    let conn = self.conn.lock().unwrap();
+    // Only notifications people actually saw count as unread. Deferred ones
+    // are counted once, by the digest that sums them up.
+    let read =
+      entry.kind == HistoryKind::Event || entry.outcome != Some(Outcome::Shown);
+    conn.execute(
+      "INSERT INTO history (created_at, kind, broadcaster_id, broadcaster_name,
+         event_type, category, title, outcome, reason, read)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+      params![
+        now_ms(),
+        entry.kind.as_str(),
+        entry.broadcaster_id,
+        entry.broadcaster_name,
+        entry.event_type,
+        entry.category,
+        entry.title,
+        entry.outcome.map(|o| o.as_str()),
+        entry.reason,
+        read,
+      ],
+    )?;
+    Ok(())
+  }
+
+  // Pages backwards from `before_id`, newest first.
+  pub fn page(
+    &self,
+    before_id: Option<i64>,
+    limit: Option<u32>,
+  ) -> rusqlite::Result<Vec<HistoryItem>> {
+    let conn = self.conn.lock().unwrap();
+    let mut stmt = conn.prepare(
+      "SELECT id, created_at, kind, broadcaster_id, broadcaster_name,
+         event_type, category, title, outcome, reason, read
+       FROM history
+       WHERE ?1 IS NULL OR id < ?1
+       ORDER BY id DESC
+       LIMIT ?2",
+    )?;
+
+    let rows = stmt.query_map(
+      params![before_id, limit.unwrap_or(DEFAULT_PAGE_SIZE)],
+      |row| {
+        Ok(HistoryItem {
+          id: row.get(0)?,
+          created_at: row.get(1)?,
+          kind: row.get(2)?,
+          broadcaster_id: row.get(3)?,
+          broadcaster_name: row.get(4)?,
+          event_type: row.get(5)?,
+          category: row.get(6)?,
+          title: row.get(7)?,
+          outcome: row.get(8)?,
+          reason: row.get(9)?,
+          read: row.get(10)?,
+        })
+      },
+    )?;
+    rows.collect()
+  }
+
+  // Marks the given items read, or everything when `ids` is None.
+  pub fn mark_read(&self, ids: Option<&[i64]>) -> rusqlite::Result<()> {
+    let conn = self.conn.lock().unwrap();
+    match ids {
+      Some(ids) => {
+        let mut stmt =
+          conn.prepare("UPDATE history SET read = 1 WHERE id = ?1")?;
+        for id in ids {
+          stmt.execute(params![id])?;
+        }
+      }
+      None => {
+        conn.execute("UPDATE history SET read = 1 WHERE read = 0", [])?;
+      }
+    }
+    Ok(())
+  }
+
+  pub fn clear(&self) -> rusqlite::Result<()> {
+    self
+      .conn
+      .lock()
+      .unwrap()
+      .execute("DELETE FROM history", [])?;
+    Ok(())
+  }
+
+  pub fn unread_count(&self) -> rusqlite::Result<u64> {
+    self.conn.lock().unwrap().query_row(
+      "SELECT COUNT(*) FROM history WHERE read = 0",
+      [],
+      |row| row.get(0),
+    )
+  }
+}
+
+pub fn record(app: &AppHandle, entry: HistoryEntry) {
+  if let Some(history) = app.try_state::<History>() {
+    // The writer only stops once the history is gone.
+    let _ = history.writer.send(entry);
+  }
+}
+
+fn write(app: &AppHandle, entry: HistoryEntry) {
+  let history = match app.try_state::<History>() {
+    Some(history) => history,
+    None => return,
+  };
+
+  if let Err(e) = history.insert(&entry) {
+    eprintln!("Failed to record history: {}", e);
+    return;
+  }
+  if entry.kind == HistoryKind::Notification {
+    refresh_unread(app);
+  }
+}
+
+// Shows the unread count next to the tray icon and tells the UI about it.
+pub fn refresh_unread(app: &AppHandle) {
+  let count = match app.try_state::<History>().map(|h| h.unread_count()) {
+    Some(Ok(count)) => count,
+    Some(Err(e)) => {
+      eprintln!("Failed to count unread history: {}", e);
+      return;
+    }
+    None => return,
+  };
+
+  if let Some(tray) = app.tray_by_id(TRAY_ID) {
+    let (title, tooltip) = if count == 0 {
+      (None, "Notisr".to_string())
+    } else {
+      (
+        Some(count.to_string()),
+        format!("Notisr - {} unread", count),
+      )
+    };
+    let _ = tray.set_title(title);
+    let _ = tray.set_tooltip(Some(tooltip));
+  }
+
+  if let Err(e) = app.emit("history:unread", count) {
+    eprintln!("Error emitting 'history:unread' event: {}", e);
+  }
+}
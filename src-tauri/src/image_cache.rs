@@ -0,0 +1,184 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+
+use crate::notifications::NotificationRequest;
+use crate::AppHandle;
+
+const CACHE_DIR: &str = "images";
+const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+const MAX_CACHE_BYTES: u64 = 50 * 1024 * 1024;
+// Twitch refreshes live previews roughly every five minutes.
+const THUMBNAIL_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+const THUMBNAIL_SIZE: (u32, u32) = (640, 360);
+
+pub struct ImageCache {
+  dir: PathBuf,
+  client: reqwest::Client,
+}
+
+fn cache_key(url: &str) -> String {
+  let digest = Sha256::digest(url.as_bytes());
+  let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+  let ext = Path::new(url.split('?').next().unwrap_or(url))
+    .extension()
+    .and_then(|e| e.to_str())
+    .filter(|e| matches!(*e, "png" | "jpg" | "jpeg" | "webp" | "gif"))
+    .unwrap_or("img");
+  format!("{}.{}", &hex[..32], ext)
+}
+
+fn age(path: &Path) -> Option<Duration> {
+  let modified = fs::metadata(path).ok()?.modified().ok()?;
+  SystemTime::now().duration_since(modified).ok()
+}
+
+// Eviction is least recently used, so a hit bumps the file's mtime.
+fn touch(path: &Path) {
+  if let Ok(file) = fs::File::options().write(true).open(path) {
+    let _ = file.set_modified(SystemTime::now());
+  }
+}
+
+impl ImageCache {
+  pub fn new(app: &AppHandle) -> anyhow::Result<Self> {
+    let dir = app.path().app_cache_dir()?.join(CACHE_DIR);
+    fs::create_dir_all(&dir)?;
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+    Ok(Self { dir, client })
+  }
+
+  pub async fn avatar(&self, url: &str) -> Option<PathBuf> {
+    self.get(url, None).await
+  }
+
+  pub async fn thumbnail(&self, login: &str) -> Option<PathBuf> {
+    let (width, height) = THUMBNAIL_SIZE;
+    let url = format!(
+      "https://static-cdn.jtvnw.net/previews-ttv/live_user_{}-{}x{}.jpg",
+      login.to_lowercase(),
+      width,
+      height
+    );
+    self.get(&url, Some(THUMBNAIL_MAX_AGE)).await
+  }
+
+  async fn get(&self, url: &str, max_age: Option<Duration>) -> Option<PathBuf> {
+    let path = self.dir.join(cache_key(url));
+    let fresh = match (age(&path), max_age) {
+      (Some(_), None) => true,
+      (Some(age), Some(max_age)) => age <= max_age,
+      (None, _) => false,
+    };
+    if fresh {
+      touch(&path);
+      return Some(path);
+    }
+
+    match self.download(url, &path).await {
+      Ok(()) => {
+        self.evict();
+        Some(path)
+      }
+      Err(e) => {
+        eprintln!("Failed to cache image {}: {}", url, e);
+        // A stale thumbnail still beats none.
+        path.exists().then_some(path)
+      }
+    }
+  }
+
+  async fn download(&self, url: &str, path: &Path) -> Result<(), String> {
+    let resp = self
+      .client
+      .get(url)
+      .send()
+      .await
+      .map_err(|e| format!("request err: {}", e))?;
+
+    if !resp.status().is_success() {
+      return Err(format!("status {}", resp.status()));
+    }
+    let is_image = resp
+      .headers()
+      .get(reqwest::header::CONTENT_TYPE)
+      .and_then(|v| v.to_str().ok())
+      .is_some_and(|v| v.starts_with("image/"));
+    if !is_image {
+      return Err("not an image".into());
+    }
+    if resp
+      .content_length()
+      .is_some_and(|len| len as usize > MAX_IMAGE_BYTES)
+    {
+      return Err("image too large".into());
+    }
+
+    let bytes = resp.bytes().await.map_err(|e| format!("read err: {}", e))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+      return Err("image too large".into());
+    }
+
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, &bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+  }
+
+  // Drops the least recently used images until the cache fits its budget.
+  fn evict(&self) {
+    let entries = match fs::read_dir(&self.dir) {
+      Ok(entries) => entries,
+      Err(_) => return,
+    };
+
+    let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+      .filter_map(|entry| {
+        let entry = entry.ok()?;
+        let meta = entry.metadata().ok()?;
+        if !meta.is_file() {
+          return None;
+        }
+        Some((entry.path(), meta.len(), meta.modified().ok()?))
+      })
+      .collect();
+
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    if total <= MAX_CACHE_BYTES {
+      return;
+    }
+
+    files.sort_by_key(|(_, _, modified)| *modified);
+    for (path, len, _) in files {
+      if total <= MAX_CACHE_BYTES {
+        break;
+      }
+      if fs::remove_file(&path).is_ok() {
+        total = total.saturating_sub(len);
+      }
+    }
+  }
+}
+
+// Fills in whatever images can be had within the fetch timeout.
+pub async fn attach_images(
+  app: &AppHandle,
+  mut request: NotificationRequest,
+  avatar_url: Option<String>,
+  with_thumbnail: bool,
+) -> NotificationRequest {
+  let cache = match app.try_state::<ImageCache>() {
+    Some(cache) => cache,
+    None => return request,
+  };
+
+  if let Some(url) = avatar_url.filter(|u| !u.is_empty()) {
+    request.avatar = cache.avatar(&url).await;
+  }
+  if with_thumbnail {
+    request.thumbnail = cache.thumbnail(&request.broadcaster_login).await;
+  }
+  request
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::settings::current_settings;
+use crate::AppHandle;
+
+// Where "open stream" goes, from notifications and the streamer list.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LaunchTarget {
+  #[default]
+  Browser,
+  // The desktop Twitch app through its `twitch://` URI.
+  App,
+  Popout,
+  // A program and its arguments, e.g. `streamlink twitch.tv/{login} best`.
+  // `{login}`, `{name}` and `{url}` are substituted per argument; without
+  // any placeholder the stream URL is passed last. Not available on Windows.
+  Command {
+    template: String,
+  },
+}
+
+pub const COMMAND_PLACEHOLDERS: &[&str] = &["login", "name", "url"];
+
+// The channel to open.
+#[derive(Debug, Clone)]
+pub struct Stream<'a> {
+  pub broadcaster_id: &'a str,
+  pub login: &'a str,
+  pub name: &'a str,
+}
+
+impl Stream<'_> {
+  pub fn url(&self) -> String {
+    format!("https://twitch.tv/{}", self.login)
+  }
+}
+
+// Splits a command template into arguments. Whitespace separates them and
+// single or double quotes group them; nothing else is special.
+fn split_args(template: &str) -> Result<Vec<String>, String> {
+  let mut args = Vec::new();
+  let mut current = String::new();
+  let mut in_arg = false;
+  let mut quote: Option<char> = None;
+
+  for c in template.chars() {
+    match quote {
+      Some(q) if c == q => quote = None,
+      Some(_) => current.push(c),
+      None if c == '"' || c == '\'' => {
+        quote = Some(c);
+        in_arg = true;
+      }
+      None if c.is_whitespace() => {
+        if in_arg {
+          args.push(std::mem::take(&mut current));
+          in_arg = false;
+        }
+      }
+      None => {
+        current.push(c);
+        in_arg = true;
+      }
+    }
+  }
+
+  if quote.is_some() {
+    return Err(format!("Unclosed quote in '{}'.", template));
+  }
+  if in_arg {
+    args.push(current);
+  }
+  Ok(args)
+}
+
+fn has_placeholder(arg: &str) -> bool {
+  COMMAND_PLACEHOLDERS
+    .iter()
+    .any(|p| arg.contains(&format!("{{{}}}", p)))
+}
+
+impl LaunchTarget {
+  pub fn validate(&self) -> Result<(), String> {
+    if let LaunchTarget::Command { template } = self {
+      if cfg!(target_os = "windows") {
+        return Err(
+          "Launch commands aren't supported on Windows, since notifications \
+           there can only open links."
+            .into(),
+        );
+      }
+      let args = split_args(template)?;
+      if args.is_empty() {
+        return Err("A launch command needs a program to run.".into());
+      }
+      if has_placeholder(&args[0]) {
+        return Err("The program to run can't be a placeholder.".into());
+      }
+    }
+    Ok(())
+  }
+
+  // The URL to open, or None for commands.
+  pub fn url(&self, stream: &Stream) -> Option<String> {
+    match self {
+      LaunchTarget::Browser => Some(stream.url()),
+      LaunchTarget::App => Some(format!("twitch://stream/{}", stream.login)),
+      LaunchTarget::Popout => Some(format!(
+        "https://www.twitch.tv/popout/{}/player",
+        stream.login
+      )),
+      LaunchTarget::Command { .. } => None,
+    }
+  }
+
+  // The program and its arguments, with placeholders filled in. Values are
+  // substituted after splitting, so they can never add arguments.
+  fn command_args(&self, stream: &Stream) -> Result<Vec<String>, String> {
+    let LaunchTarget::Command { template } = self else {
+      return Err("Not a command launch target.".into());
+    };
+    let url = stream.url();
+    let mut args = split_args(template)?;
+    let substituted = args.iter().skip(1).any(|arg| has_placeholder(arg));
+
+    for arg in args.iter_mut() {
+      *arg = arg
+        .replace("{login}", stream.login)
+        .replace("{name}", stream.name)
+        .replace("{url}", &url);
+    }
+    if !substituted {
+      args.push(url);
+    }
+    Ok(args)
+  }
+
+  pub fn launch(&self, app: &AppHandle, stream: &Stream) -> Result<(), String> {
+    if let Some(url) = self.url(stream) {
+      return app
+        .opener()
+        .open_url(url, None::<&str>)
+        .map_err(|e| e.to_string());
+    }
+
+    let args = self.command_args(stream)?;
+    let (program, args) = args.split_first().ok_or("Empty launch command.")?;
+    Command::new(program)
+      .args(args)
+      .stdin(Stdio::null())
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .spawn()
+      .map(|mut child| {
+        // Reap the player when it exits.
+        std::thread::spawn(move || child.wait());
+      })
+      .map_err(|e| format!("Failed to run '{}': {}", program, e))
+  }
+}
+
+// The streamer's own launch target if one is set, the user's otherwise.
+pub fn launch_target(app: &AppHandle, broadcaster_id: &str) -> LaunchTarget {
+  let settings = current_settings(app);
+  settings
+    .launch_overrides
+    .get(broadcaster_id)
+    .cloned()
+    .unwrap_or(settings.launch_target)
+}
+
+pub fn open_stream(app: &AppHandle, stream: &Stream) {
+  let target = launch_target(app, stream.broadcaster_id);
+  if let Err(e) = target.launch(app, stream) {
+    eprintln!("Failed to open {}: {}", stream.name, e);
+    // Still get the user to the stream.
+    if target != LaunchTarget::Browser {
+      let _ = LaunchTarget::Browser.launch(app, stream);
+    }
+  }
+}
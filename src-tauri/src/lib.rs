@@ -1,13 +1,25 @@
 mod appsync;
 pub mod command;
-mod notifications;
+mod config;
+mod favorites;
+mod history;
+mod image_cache;
+mod launch;
+pub mod notifications;
 mod oauth;
+mod quiet;
+mod rules;
+mod settings;
+mod snooze;
+mod storage;
+mod templates;
+mod token;
 mod twitch;
 mod util;
+mod watchlist;
 
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
-use dotenvy_macro::dotenv;
 use keyring_core::Result;
 use reqwest::blocking::Client as BlockingClient;
 use rouille::{router, Response, Server};
@@ -16,22 +28,57 @@ use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use tauri::menu::{Menu, MenuItem};
+use tauri::menu::{CheckMenuItem, Menu, MenuItem};
 use tauri::tray::{
   MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent,
 };
 use tauri::{
-  include_image, AppHandle, Emitter, LogicalPosition, Manager, PhysicalSize,
-  RunEvent, WebviewUrl, WebviewWindow, WindowEvent,
+  include_image, Emitter, Listener, LogicalPosition, Manager, PhysicalSize,
+  RunEvent, WebviewUrl, WindowEvent,
 };
 use tauri_plugin_notification::{NotificationExt, PermissionState};
 
-use crate::appsync::{start_ws_client, stop_ws_client};
+use crate::appsync::{
+  is_paused, pause_ws_client, reconnect_ws_client, resume_ws_client,
+  start_ws_client, stop_ws_client, LiveSince,
+};
 use crate::command::{
-  fetch_streamers, login, on_startup, open_broadcaster_url, shutdown_server,
+  add_rule, add_template, add_to_watch_list, clear_history, delete_rule,
+  delete_template, fetch_streamers, force_reconnect, get_connection_status,
+  get_favorites, get_history, get_settings, get_snoozed,
+  get_subscription_health, get_unread_count, get_watch_list, get_worker_status,
+  list_rules, list_templates, login, mark_history_read, mute_streamer,
+  on_startup, open_broadcaster_url, pause_notifications, preview_template,
+  remove_from_watch_list, resume_notifications, set_do_not_disturb,
+  set_favorite, set_offline_notifications, shutdown_server, snooze_streamer,
+  test_rules, unsnooze_streamer, update_rule, update_settings, update_template,
   ServerCtl,
 };
+use crate::config::AppConfig;
+use crate::favorites::Favorites;
+use crate::history::{refresh_unread, History, TRAY_ID};
+use crate::image_cache::ImageCache;
+use crate::notifications::Notifier;
+use crate::quiet::{
+  set_do_not_disturb as apply_do_not_disturb, start_digest_loop,
+};
+use crate::rules::RuleSet;
+use crate::settings::{current_settings, Settings};
+use crate::snooze::Snoozes;
+use crate::templates::TemplateSet;
+use crate::token::{start_token_lifecycle, SharedToken};
 use crate::util::{check_validitiy_token, spawn_new_user};
+use crate::watchlist::WatchList;
+
+// Unit tests run on Tauri's mock runtime, so state can be managed and the
+// notification pipeline driven without a window or a desktop session.
+#[cfg(not(test))]
+pub type AppRuntime = tauri::Wry;
+#[cfg(test)]
+pub type AppRuntime = tauri::test::MockRuntime;
+
+pub type AppHandle = tauri::AppHandle<AppRuntime>;
+type WebviewWindow = tauri::WebviewWindow<AppRuntime>;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct UserInfo {
@@ -64,6 +111,16 @@ pub fn set_platform_default_store() -> Result<()> {
   Ok(())
 }
 
+#[cfg(target_os = "linux")]
+pub fn set_platform_default_store() -> Result<()> {
+  #[cfg(not(debug_assertions))]
+  {
+    let store = dbus_secret_service_keyring_store::Store::new()?;
+    keyring_core::set_default_store(store);
+  }
+  Ok(())
+}
+
 fn set_window_size(window: &WebviewWindow) {
   let opt_monitor = window.current_monitor().unwrap();
 
@@ -84,6 +141,11 @@ fn set_window_size(window: &WebviewWindow) {
   {
     window_height = monitor.size().height as f64;
   }
+  #[cfg(target_os = "linux")]
+  {
+    // Panels and docks differ per desktop, so use what the WM leaves us.
+    window_height = monitor.work_area().size.height as f64;
+  }
 
   window
     .set_size(PhysicalSize {
@@ -106,18 +168,38 @@ fn set_window_position(window: &WebviewWindow) {
   let scale = window.scale_factor().unwrap_or(1.0);
   let window_size = window.inner_size().unwrap().width as f64 / scale;
 
-  let x = (monitor_size / scale) - window_size;
-  let y = 0.0;
+  #[cfg(not(target_os = "linux"))]
+  let (x, y) = ((monitor_size / scale) - window_size, 0.0);
+  #[cfg(target_os = "linux")]
+  let (x, y) = {
+    // Stay clear of top bars and side docks.
+    let area = monitor.work_area();
+    let right = (area.position.x as f64 + area.size.width as f64) / scale;
+    (right - window_size, area.position.y as f64 / scale)
+  };
 
   window.set_position(LogicalPosition { x: x, y: y }).unwrap();
 }
 
+fn pause_item_text(paused: bool) -> &'static str {
+  if paused {
+    "Resume notifications"
+  } else {
+    "Pause notifications"
+  }
+}
+
 fn handle_setup_user(
   app: AppHandle,
   csrf_state: String,
   code_verifier: Arc<Mutex<Option<String>>>,
 ) -> ServerCtl {
-  let server = Server::new("127.0.0.1:1337", move |request| {
+  let config = app.state::<AppConfig>().inner().clone();
+  // Twitch sends the user back to the configured redirect_uri.
+  let address = config
+    .callback_address()
+    .expect("redirect_uri was validated when the config was loaded");
+  let server = Server::new(address, move |request| {
         router!(request,
             (GET) (/) => {
                 let qs = request.raw_query_string();
@@ -147,21 +229,17 @@ fn handle_setup_user(
                     lock.take().expect("PKCE verifier already consumed")
                 };
 
-                let client_id = dotenv!("CLIENT_ID");
-                let client_secret = dotenv!("CLIENT_SECRET");
-                let redirect_uri = dotenv!("REDIRECT_URI");
-
                 let http_client = BlockingClient::new();
                 let params = [
-                    ("client_id", client_id),
-                    ("client_secret", client_secret),
+                    ("client_id", config.client_id.as_str()),
+                    ("client_secret", config.client_secret.as_str()),
                     ("grant_type", "authorization_code"),
                     ("code", code.as_str()),
-                    ("redirect_uri", redirect_uri),
+                    ("redirect_uri", config.redirect_uri.as_str()),
                     ("code_verifier", verifier.as_str()),
                 ];
 
-                let resp = match http_client.post("https://id.twitch.tv/oauth2/token").form(&params).send() {
+                let resp = match http_client.post(format!("{}/oauth2/token", config.twitch_id_uri)).form(&params).send() {
                     Ok(r) => r,
                     Err(e) => return Response::text(format!("Network error: {:?}", e)).with_status_code(500)
                 };
@@ -183,7 +261,7 @@ fn handle_setup_user(
                     };
 
                     let client = BlockingClient::new();
-                    let validation_response = match client.get("https://id.twitch.tv/oauth2/validate").header("Authorization", format!("Bearer {}", access_token)).send() {
+                    let validation_response = match client.get(format!("{}/oauth2/validate", config.twitch_id_uri)).header("Authorization", format!("Bearer {}", access_token)).send() {
                       Ok(r) => {r},
                       Err(e) => {return Response::text(format!("Network error: {:?}", e)).with_status_code(500)},
                     };
@@ -282,19 +360,73 @@ fn handle_setup_user(
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  let builder = tauri::Builder::default()
+  let builder = tauri::Builder::<AppRuntime>::new()
     .plugin(tauri_plugin_notification::init())
     .setup(|app| {
       set_platform_default_store()?;
+      let config = AppConfig::load(app.handle())?;
       let show_menu_on_left_click = cfg!(target_os = "macos");
+      app.manage(Mutex::new(Settings::load(app.handle())));
+      app.manage(Notifier::platform_default());
+      match ImageCache::new(app.handle()) {
+        Ok(cache) => {
+          app.manage(cache);
+        }
+        Err(e) => eprintln!("Image cache is unavailable: {}", e),
+      }
 
       let quit_item =
         MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
       let show_item =
         MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
-
-      let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
-      let decision = check_validitiy_token();
+      let pause_item = MenuItem::with_id(
+        app,
+        "toggle_pause",
+        pause_item_text(is_paused()),
+        true,
+        None::<&str>,
+      )?;
+      let reconnect_item =
+        MenuItem::with_id(app, "reconnect", "Reconnect", true, None::<&str>)?;
+      let dnd_item = CheckMenuItem::with_id(
+        app,
+        "toggle_dnd",
+        "Do not disturb",
+        true,
+        current_settings(app.handle()).do_not_disturb,
+        None::<&str>,
+      )?;
+
+      let menu = Menu::with_items(
+        app,
+        &[
+          &show_item,
+          &pause_item,
+          &dnd_item,
+          &reconnect_item,
+          &quit_item,
+        ],
+      )?;
+
+      // The worker reports pause changes whether they came from the tray or
+      // the UI, so the label follows that rather than the click.
+      app.listen("notifications:paused", move |event| {
+        let paused = event.payload() == "true";
+        let _ = pause_item.set_text(pause_item_text(paused));
+      });
+      app.listen("dnd:changed", move |event| {
+        let _ = dnd_item.set_checked(event.payload() == "true");
+      });
+      let decision = check_validitiy_token(&config);
+      app.manage(config);
+      app.manage(SharedToken::new(decision.clone().unwrap_or_default()));
+      app.manage(Mutex::new(WatchList::load(app.handle())));
+      app.manage(Mutex::new(RuleSet::load(app.handle())));
+      app.manage(Mutex::new(Favorites::load(app.handle())));
+      app.manage(Mutex::new(Snoozes::load(app.handle())));
+      app.manage(Mutex::new(TemplateSet::load(app.handle())));
+      app.manage(Mutex::new(LiveSince::default()));
+      start_digest_loop(app.handle().clone());
       let needs_login = decision.is_none();
       let main_window = tauri::WebviewWindowBuilder::new(
         app,
@@ -308,7 +440,7 @@ pub fn run() {
       .build()
       .unwrap();
 
-      let mut tray_builder = TrayIconBuilder::new();
+      let mut tray_builder = TrayIconBuilder::with_id(TRAY_ID);
       let bundle_name = "com.y2kforever.notisr";
 
       #[cfg(target_os = "macos")]
@@ -338,6 +470,27 @@ pub fn run() {
               let _ = window.set_focus();
             }
           }
+          "toggle_pause" => {
+            let result = if is_paused() {
+              resume_ws_client()
+            } else {
+              pause_ws_client()
+            };
+            if let Err(e) = result {
+              eprintln!("Failed to toggle notifications: {}", e);
+            }
+          }
+          "toggle_dnd" => {
+            let enabled = !current_settings(app).do_not_disturb;
+            if let Err(e) = apply_do_not_disturb(app, enabled) {
+              eprintln!("Failed to toggle do not disturb: {}", e);
+            }
+          }
+          "reconnect" => {
+            if let Err(e) = reconnect_ws_client() {
+              eprintln!("Failed to reconnect: {}", e);
+            }
+          }
           "quit" => app.exit(0),
           _ => {}
         })
@@ -363,6 +516,14 @@ pub fn run() {
         })
         .build(app)?;
 
+      match History::open(app.handle()) {
+        Ok(history) => {
+          app.manage(history);
+          refresh_unread(app.handle());
+        }
+        Err(e) => eprintln!("History is unavailable: {}", e),
+      }
+
       let auth_state: Mutex<Option<String>> = Mutex::new(None);
       app.manage(auth_state);
 
@@ -378,6 +539,7 @@ pub fn run() {
         if let Some(token) = &decision {
           let token = token.clone();
           let app_handle = app.handle().clone();
+          start_token_lifecycle(app_handle.clone());
           std::thread::spawn(move || {
             if let Err(e) = start_ws_client(app_handle, token) {
               eprintln!("WebSocket client failed to start: {:?}", e);
@@ -410,7 +572,40 @@ pub fn run() {
       on_startup,
       login,
       open_broadcaster_url,
-      fetch_streamers
+      fetch_streamers,
+      get_connection_status,
+      get_subscription_health,
+      get_worker_status,
+      pause_notifications,
+      resume_notifications,
+      force_reconnect,
+      get_watch_list,
+      add_to_watch_list,
+      remove_from_watch_list,
+      get_favorites,
+      set_favorite,
+      get_snoozed,
+      snooze_streamer,
+      mute_streamer,
+      unsnooze_streamer,
+      list_rules,
+      add_rule,
+      update_rule,
+      delete_rule,
+      test_rules,
+      list_templates,
+      add_template,
+      update_template,
+      delete_template,
+      preview_template,
+      get_settings,
+      update_settings,
+      set_do_not_disturb,
+      set_offline_notifications,
+      get_history,
+      mark_history_read,
+      clear_history,
+      get_unread_count
     ]);
 
   let context = tauri::generate_context!();
@@ -0,0 +1,55 @@
+use super::{NotificationBackend, NotificationRequest};
+use crate::launch::open_stream;
+use crate::AppHandle;
+use notify_rust::{Notification, Timeout, Urgency};
+
+pub struct LinuxBackend;
+
+impl NotificationBackend for LinuxBackend {
+  fn name(&self) -> &'static str {
+    "D-Bus"
+  }
+
+  fn deliver(
+    &self,
+    app: &AppHandle,
+    request: &NotificationRequest,
+  ) -> Result<(), String> {
+    let mut notification = Notification::new();
+    notification
+      .appname("Notisr")
+      .summary(&request.title)
+      .body(&request.body)
+      // "default" is what most servers send when the toast itself is clicked.
+      .action("default", "Open stream");
+
+    if request.priority {
+      notification
+        .urgency(Urgency::Critical)
+        .timeout(Timeout::Never)
+        .sound_name("message-new-instant");
+    }
+
+    if let Some(avatar) = request.avatar.as_ref().and_then(|p| p.to_str()) {
+      notification.icon(avatar);
+    }
+    if let Some(thumbnail) = request.thumbnail.as_ref().and_then(|p| p.to_str())
+    {
+      notification.image_path(thumbnail);
+    }
+
+    let handle = notification.show().map_err(|e| e.to_string())?;
+
+    // The toast is up; waiting for the click happens on its own thread.
+    let app = app.clone();
+    let request = request.clone();
+    std::thread::spawn(move || {
+      handle.wait_for_action(|action| {
+        if action == "default" {
+          open_stream(&app, &request.stream());
+        }
+      });
+    });
+    Ok(())
+  }
+}
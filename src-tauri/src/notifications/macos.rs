@@ -0,0 +1,84 @@
+use super::{NotificationBackend, NotificationRequest};
+use crate::launch::open_stream;
+use crate::AppHandle;
+use mac_notification_sys::{MainButton, Notification, NotificationResponse};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+
+// How long a toast gets to fail before we take it as posted.
+const POST_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct MacBackend;
+
+fn send(request: &NotificationRequest) -> Result<NotificationResponse, String> {
+  let mut notification = Notification::new();
+  notification
+    .main_button(MainButton::SingleAction("Open stream"))
+    .title(&request.title)
+    .message(&request.body);
+
+  if request.priority {
+    // The close button turns the banner into an alert, which macOS keeps
+    // on screen as long as the user allows alerts for the app.
+    notification.sound("Glass").close_button("Dismiss");
+  } else {
+    notification.default_sound();
+  }
+
+  let image = request.thumbnail.as_ref().or(request.avatar.as_ref());
+  if let Some(image) = image.and_then(|p| p.to_str()) {
+    notification.content_image(image);
+  }
+
+  notification.send().map_err(|e| format!("{:?}", e))
+}
+
+impl NotificationBackend for MacBackend {
+  fn name(&self) -> &'static str {
+    "macOS"
+  }
+
+  fn deliver(
+    &self,
+    app: &AppHandle,
+    request: &NotificationRequest,
+  ) -> Result<(), String> {
+    // Sending only returns once the user has dealt with the toast, so it
+    // runs on its own thread and handles the click there.
+    let (posted, posted_rx) = channel();
+    let app = app.clone();
+    let request = request.clone();
+    std::thread::spawn(move || {
+      let response = match send(&request) {
+        Ok(response) => response,
+        Err(e) => {
+          let _ = posted.send(Err(e));
+          return;
+        }
+      };
+      let _ = posted.send(Ok(()));
+      match response {
+        NotificationResponse::ActionButton(_) | NotificationResponse::Click => {
+          open_stream(&app, &request.stream());
+        }
+        NotificationResponse::CloseButton(_)
+        | NotificationResponse::None
+        | NotificationResponse::Reply(_) => {}
+      }
+    });
+
+    // Failures come back right away and go to the next backend; a toast
+    // that's still up is posted.
+    match posted_rx.recv_timeout(POST_TIMEOUT) {
+      Ok(result) => result,
+      Err(RecvTimeoutError::Timeout) => Ok(()),
+      Err(RecvTimeoutError::Disconnected) => {
+        Err("notification thread stopped".into())
+      }
+    }
+  }
+
+  fn blocks(&self) -> bool {
+    true
+  }
+}
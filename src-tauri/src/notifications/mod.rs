@@ -0,0 +1,139 @@
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+mod plugin;
+#[cfg(test)]
+mod recording;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use crate::launch::Stream;
+use crate::AppHandle;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::Manager;
+
+pub use plugin::PluginBackend;
+#[cfg(test)]
+pub use recording::RecordingBackend;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationRequest {
+  pub title: String,
+  pub body: String,
+  pub broadcaster_id: String,
+  pub broadcaster_login: String,
+  pub broadcaster_name: String,
+  // Cached files; backends that can't show images ignore them.
+  pub avatar: Option<PathBuf>,
+  pub thumbnail: Option<PathBuf>,
+  // Favorites going live or changing their channel get their own sound and
+  // stay on screen until dismissed.
+  pub priority: bool,
+}
+
+impl NotificationRequest {
+  pub fn stream(&self) -> Stream<'_> {
+    Stream {
+      broadcaster_id: &self.broadcaster_id,
+      login: &self.broadcaster_login,
+      name: &self.broadcaster_name,
+    }
+  }
+}
+
+pub trait NotificationBackend: Send + Sync {
+  fn name(&self) -> &'static str;
+
+  // Shows the notification. An error hands it to the next backend in the
+  // chain.
+  fn deliver(
+    &self,
+    app: &AppHandle,
+    request: &NotificationRequest,
+  ) -> Result<(), String>;
+
+  // Backends that wait on the platform before returning must not run on the
+  // caller's thread. Clicks are handled after `deliver` returns.
+  fn blocks(&self) -> bool {
+    false
+  }
+}
+
+// Tries each backend in order until one delivers.
+#[derive(Clone)]
+pub struct Notifier {
+  backends: Vec<Arc<dyn NotificationBackend>>,
+}
+
+impl Notifier {
+  pub fn new(backends: Vec<Arc<dyn NotificationBackend>>) -> Self {
+    Self { backends }
+  }
+
+  // The native backend for this platform, falling back to the Tauri plugin.
+  pub fn platform_default() -> Self {
+    #[allow(unused_mut)]
+    let mut backends: Vec<Arc<dyn NotificationBackend>> = Vec::new();
+    #[cfg(target_os = "macos")]
+    backends.push(Arc::new(macos::MacBackend));
+    #[cfg(target_os = "windows")]
+    backends.push(Arc::new(windows::WindowsBackend));
+    #[cfg(target_os = "linux")]
+    backends.push(Arc::new(linux::LinuxBackend));
+    backends.push(Arc::new(PluginBackend));
+
+    Self::new(backends)
+  }
+
+  // Resolves once a backend has shown the notification, or with the last
+  // error if none could.
+  pub async fn notify(
+    &self,
+    app: &AppHandle,
+    request: NotificationRequest,
+  ) -> Result<(), String> {
+    if self.backends.iter().any(|b| b.blocks()) {
+      let backends = self.backends.clone();
+      let app = app.clone();
+      tauri::async_runtime::spawn_blocking(move || {
+        deliver_with(&backends, &app, &request)
+      })
+      .await
+      .map_err(|e| e.to_string())?
+    } else {
+      deliver_with(&self.backends, app, &request)
+    }
+  }
+}
+
+fn deliver_with(
+  backends: &[Arc<dyn NotificationBackend>],
+  app: &AppHandle,
+  request: &NotificationRequest,
+) -> Result<(), String> {
+  let mut last_error = "no notification backend".to_string();
+  for backend in backends {
+    match backend.deliver(app, request) {
+      Ok(()) => return Ok(()),
+      Err(e) => {
+        eprintln!("{} notification failed: {}", backend.name(), e);
+        last_error = e;
+      }
+    }
+  }
+  eprintln!("No notification backend could deliver '{}'.", request.title);
+  Err(last_error)
+}
+
+pub async fn notify(
+  app: &AppHandle,
+  request: NotificationRequest,
+) -> Result<(), String> {
+  let notifier = match app.try_state::<Notifier>() {
+    Some(notifier) => notifier.inner().clone(),
+    None => Notifier::platform_default(),
+  };
+  notifier.notify(app, request).await
+}
@@ -0,0 +1,27 @@
+use super::{NotificationBackend, NotificationRequest};
+use crate::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+// Plain notification through tauri-plugin-notification. It can't open the
+// stream on click, so it's only used as the last resort.
+pub struct PluginBackend;
+
+impl NotificationBackend for PluginBackend {
+  fn name(&self) -> &'static str {
+    "Tauri"
+  }
+
+  fn deliver(
+    &self,
+    app: &AppHandle,
+    request: &NotificationRequest,
+  ) -> Result<(), String> {
+    app
+      .notification()
+      .builder()
+      .title(&request.title)
+      .body(&request.body)
+      .show()
+      .map_err(|e| e.to_string())
+  }
+}
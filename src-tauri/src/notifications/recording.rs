@@ -0,0 +1,31 @@
+use super::{NotificationBackend, NotificationRequest};
+use crate::AppHandle;
+use std::sync::Mutex;
+
+// Keeps notifications in memory instead of showing them, so the pipeline can
+// be exercised without a desktop session.
+#[derive(Default)]
+pub struct RecordingBackend {
+  delivered: Mutex<Vec<NotificationRequest>>,
+}
+
+impl RecordingBackend {
+  pub fn delivered(&self) -> Vec<NotificationRequest> {
+    self.delivered.lock().unwrap().clone()
+  }
+}
+
+impl NotificationBackend for RecordingBackend {
+  fn name(&self) -> &'static str {
+    "Recording"
+  }
+
+  fn deliver(
+    &self,
+    _app: &AppHandle,
+    request: &NotificationRequest,
+  ) -> Result<(), String> {
+    self.delivered.lock().unwrap().push(request.clone());
+    Ok(())
+  }
+}
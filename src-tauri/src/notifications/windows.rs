@@ -0,0 +1,49 @@
+use super::{NotificationBackend, NotificationRequest};
+use crate::launch::launch_target;
+use crate::AppHandle;
+use universal_notifications::Windows::{
+  ActivationType, Duration, IconCrop, Scenario, Sound, Toast,
+};
+
+pub struct WindowsBackend;
+
+impl NotificationBackend for WindowsBackend {
+  fn name(&self) -> &'static str {
+    "Windows"
+  }
+
+  fn deliver(
+    &self,
+    app: &AppHandle,
+    request: &NotificationRequest,
+  ) -> Result<(), String> {
+    // Reminder toasts stay on screen until they're dismissed.
+    let (sound, scenario) = if request.priority {
+      (Sound::Reminder, Scenario::Reminder)
+    } else {
+      (Sound::Default, Scenario::Default)
+    };
+
+    // Toasts can only activate a URL. Launch commands are rejected on Windows,
+    // so the browser is only a fallback here.
+    let stream = request.stream();
+    let url = launch_target(app, stream.broadcaster_id)
+      .url(&stream)
+      .unwrap_or_else(|| stream.url());
+
+    let mut toast = Toast::new("com.y2kforever.notisr")
+      .title(&request.title)
+      .description(&request.body)
+      .duration(Duration::Long)
+      .scenario(scenario)
+      .sound(Some(sound))
+      .action("Open Stream", &url, ActivationType::Protocol);
+    if let Some(avatar) = &request.avatar {
+      toast = toast.icon(avatar, IconCrop::Circular, &request.broadcaster_name);
+    }
+    if let Some(thumbnail) = &request.thumbnail {
+      toast = toast.image(thumbnail, &request.title);
+    }
+    toast.show().map_err(|e| format!("{:?}", e))
+  }
+}
@@ -1,6 +1,6 @@
+use crate::config::AppConfig;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
-use dotenvy_macro::dotenv;
 use rand::RngCore;
 use reqwest::blocking::Client as BlockingClient;
 use serde::Deserialize;
@@ -11,7 +11,6 @@ use std::{
 };
 
 #[derive(Deserialize, Debug)]
-#[allow(dead_code)]
 pub struct ValidateResp {
   pub expires_in: Option<u64>,
 }
@@ -40,11 +39,12 @@ pub fn generate_pkce_pair() -> (String, String) {
 }
 
 pub fn validate_access_token(
+  config: &AppConfig,
   access_token: &str,
 ) -> Result<Option<ValidateResp>, Box<dyn Error>> {
   let client = BlockingClient::new();
   let resp = client
-    .get("https://id.twitch.tv/oauth2/validate")
+    .get(format!("{}/oauth2/validate", config.twitch_id_uri))
     .header("Authorization", format!("OAuth {}", access_token))
     .send()?;
 
@@ -62,21 +62,19 @@ pub fn validate_access_token(
 }
 
 pub fn refresh_access_token(
+  config: &AppConfig,
   refresh_token: &str,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
-  let client_id = dotenv!("CLIENT_ID");
-  let client_secret = dotenv!("CLIENT_SECRET");
-
   let params = [
-    ("client_id", client_id),
-    ("client_secret", client_secret),
+    ("client_id", config.client_id.as_str()),
+    ("client_secret", config.client_secret.as_str()),
     ("grant_type", "refresh_token"),
     ("refresh_token", refresh_token),
   ];
 
   let client = BlockingClient::new();
   let resp = client
-    .post("https://id.twitch.tv/oauth2/token")
+    .post(format!("{}/oauth2/token", config.twitch_id_uri))
     .form(&params)
     .send()
     .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
@@ -124,12 +122,12 @@ pub fn refresh_access_token(
     {
       // DEVELOPMENT
       use crate::dev_store::DevEntry;
-      DevEntry::new("notisr", "access_token")
+      DevEntry::new("notisr", "refresh_token")
         .set_secret(raw.refresh_token.as_bytes())
         .unwrap();
     }
 
-    return Ok(raw.refresh_token);
+    return Ok(raw.access_token);
   }
 
   if status.as_u16() == 401 {
@@ -0,0 +1,229 @@
+use chrono::{Datelike, Local, NaiveDateTime};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::config::AppConfig;
+use crate::history::{record, HistoryEntry, HistoryKind, Outcome};
+use crate::notifications::{notify, NotificationRequest};
+use crate::settings::{current_settings, parse_time, QuietPeriod, Settings};
+use crate::snooze::is_silenced;
+use crate::storage::edit_persisted;
+use crate::twitch::fetch_streamer_snapshots;
+use crate::AppHandle;
+
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct DeferredNotification {
+  pub broadcaster_id: String,
+  pub broadcaster_login: String,
+  pub broadcaster_name: String,
+  pub update_type: String,
+}
+
+static DEFERRED: OnceLock<Mutex<Vec<DeferredNotification>>> = OnceLock::new();
+static DIGEST_LOOP_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn deferred() -> &'static Mutex<Vec<DeferredNotification>> {
+  DEFERRED.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn period_covers(period: &QuietPeriod, now: NaiveDateTime) -> bool {
+  let (start, end) = match (parse_time(&period.start), parse_time(&period.end))
+  {
+    (Ok(start), Ok(end)) => (start, end),
+    _ => return false,
+  };
+  let today = now.weekday();
+  let time = now.time();
+
+  if start < end {
+    period.days.contains(&today) && time >= start && time < end
+  } else {
+    // Overnight: the evening belongs to today, the morning to yesterday.
+    (period.days.contains(&today) && time >= start)
+      || (period.days.contains(&today.pred()) && time < end)
+  }
+}
+
+fn in_quiet_hours(settings: &Settings, now: NaiveDateTime) -> bool {
+  settings.quiet_hours_enabled
+    && settings.quiet_hours.iter().any(|p| period_covers(p, now))
+}
+
+pub fn is_quiet(app: &AppHandle) -> bool {
+  let settings = current_settings(app);
+  settings.do_not_disturb
+    || in_quiet_hours(&settings, Local::now().naive_local())
+}
+
+pub fn defer(notification: DeferredNotification) {
+  println!(
+    "Quiet time, deferring '{}' notification for {}.",
+    notification.update_type, notification.broadcaster_name
+  );
+  deferred().lock().unwrap().push(notification);
+}
+
+pub fn set_do_not_disturb(
+  app: &AppHandle,
+  enabled: bool,
+) -> Result<(), String> {
+  edit_persisted(app, |settings: &mut Settings| {
+    settings.do_not_disturb = enabled;
+    Ok(())
+  })?;
+  emit_do_not_disturb(app, enabled);
+
+  if !enabled {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { send_digest_if_due(&app).await });
+  }
+  Ok(())
+}
+
+pub fn emit_do_not_disturb(app: &AppHandle, enabled: bool) {
+  if let Err(e) = app.emit("dnd:changed", enabled) {
+    eprintln!("Error emitting 'dnd:changed' event: {}", e);
+  }
+}
+
+pub fn start_digest_loop(app: AppHandle) {
+  if DIGEST_LOOP_RUNNING.swap(true, Ordering::SeqCst) {
+    return;
+  }
+
+  tauri::async_runtime::spawn(async move {
+    let mut interval = tokio::time::interval(DIGEST_CHECK_INTERVAL);
+    loop {
+      interval.tick().await;
+      send_digest_if_due(&app).await;
+    }
+  });
+}
+
+async fn send_digest_if_due(app: &AppHandle) {
+  if is_quiet(app) {
+    return;
+  }
+  let mut queued = std::mem::take(&mut *deferred().lock().unwrap());
+  // Streamers snoozed during quiet time stay quiet.
+  queued.retain(|n| !is_silenced(app, &n.broadcaster_id));
+  if queued.is_empty() {
+    return;
+  }
+
+  let mut went_live: Vec<&DeferredNotification> = Vec::new();
+  let mut went_offline: Vec<String> = Vec::new();
+  let mut channel_updates = 0;
+  for n in &queued {
+    match n.update_type.as_str() {
+      "status" => {
+        if !went_live
+          .iter()
+          .any(|w| w.broadcaster_id == n.broadcaster_id)
+        {
+          went_live.push(n);
+        }
+      }
+      "offline" => {
+        if !went_offline.contains(&n.broadcaster_name) {
+          went_offline.push(n.broadcaster_name.clone());
+        }
+      }
+      "channel_updated" => channel_updates += 1,
+      _ => {}
+    }
+  }
+
+  let ids: Vec<String> =
+    went_live.iter().map(|n| n.broadcaster_id.clone()).collect();
+  let still_live: BTreeMap<String, String> = if ids.is_empty() {
+    BTreeMap::new()
+  } else {
+    let config = app.state::<AppConfig>().inner().clone();
+    match fetch_streamer_snapshots(&config, &ids).await {
+      Ok(snapshots) => snapshots
+        .into_iter()
+        .filter(|s| s.is_live)
+        .map(|s| (s.broadcaster_name, s.broadcaster_id))
+        .collect(),
+      Err(e) => {
+        eprintln!("Failed to check who is still live: {}", e);
+        BTreeMap::new()
+      }
+    }
+  };
+
+  let mut lines = Vec::new();
+  if !went_live.is_empty() {
+    let names: Vec<&str> = went_live
+      .iter()
+      .map(|n| n.broadcaster_name.as_str())
+      .collect();
+    lines.push(format!("Went live: {}", names.join(", ")));
+  }
+  if !still_live.is_empty() {
+    let names: Vec<&str> = still_live.keys().map(String::as_str).collect();
+    lines.push(format!("Still live: {}", names.join(", ")));
+  }
+  if !went_offline.is_empty() {
+    lines.push(format!("Went offline: {}", went_offline.join(", ")));
+  }
+  if channel_updates > 0 {
+    lines.push(format!("{} channel update(s)", channel_updates));
+  }
+
+  // Clicking the digest opens someone who's actually still streaming.
+  let open = still_live
+    .values()
+    .find_map(|id| went_live.iter().find(|n| &n.broadcaster_id == id))
+    .or_else(|| went_live.first());
+  let (broadcaster_id, broadcaster_login, broadcaster_name) = open
+    .map(|n| {
+      (
+        n.broadcaster_id.clone(),
+        n.broadcaster_login.clone(),
+        n.broadcaster_name.clone(),
+      )
+    })
+    .unwrap_or_default();
+
+  println!(
+    "Sending digest for {} deferred notification(s).",
+    queued.len()
+  );
+  let heading = "While you were away".to_string();
+  let body = lines.join("\n");
+  let request = NotificationRequest {
+    title: heading.clone(),
+    body: body.clone(),
+    broadcaster_id,
+    broadcaster_login,
+    broadcaster_name,
+    avatar: None,
+    thumbnail: None,
+    priority: false,
+  };
+  let delivered = notify(app, request).await;
+  let (outcome, reason) = match delivered {
+    Ok(()) => (Outcome::Shown, None),
+    Err(e) => (Outcome::Suppressed, Some(format!("delivery failed: {}", e))),
+  };
+  record(
+    app,
+    HistoryEntry {
+      kind: HistoryKind::Notification,
+      broadcaster_id: String::new(),
+      broadcaster_name: String::new(),
+      event_type: "digest".to_string(),
+      category: heading,
+      title: body,
+      outcome: Some(outcome),
+      reason,
+    },
+  );
+}
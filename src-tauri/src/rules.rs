@@ -0,0 +1,228 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::Manager;
+use uuid::Uuid;
+
+use crate::storage::{load_json, Persist};
+use crate::AppHandle;
+
+const RULES_FILE: &str = "rules.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+  // Notify when the rule matches.
+  Allow,
+  // Never notify when the rule matches.
+  Deny,
+  // For events the rule targets, notify only when its conditions hold.
+  Only,
+}
+
+fn enabled_by_default() -> bool {
+  true
+}
+
+// A rule targets an event through `broadcaster` and `event_types`, and
+// matches it when `category`, `title_keywords` and `title_regex` hold as
+// well. Empty fields match anything.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rule {
+  #[serde(default)]
+  pub id: String,
+  #[serde(default)]
+  pub name: String,
+  #[serde(default = "enabled_by_default")]
+  pub enabled: bool,
+  pub action: RuleAction,
+  #[serde(default)]
+  pub broadcaster: Option<String>,
+  #[serde(default)]
+  pub event_types: Vec<String>,
+  #[serde(default)]
+  pub category: Option<String>,
+  #[serde(default)]
+  pub title_keywords: Vec<String>,
+  #[serde(default)]
+  pub title_regex: Option<String>,
+  #[serde(skip)]
+  compiled_regex: Option<Regex>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuleEvent {
+  pub broadcaster_id: String,
+  pub broadcaster_name: String,
+  #[serde(default)]
+  pub category: String,
+  #[serde(default)]
+  pub title: String,
+  pub event_type: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RuleVerdict {
+  pub notify: bool,
+  pub rule_id: Option<String>,
+  pub rule_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct RuleSet {
+  pub rules: Vec<Rule>,
+}
+
+fn is_blank(value: &Option<String>) -> bool {
+  value.as_deref().is_none_or(|v| v.trim().is_empty())
+}
+
+impl Rule {
+  // Validates the rule and compiles its regex. Must be called before the
+  // rule is evaluated.
+  pub fn prepare(&mut self) -> Result<(), String> {
+    self.compiled_regex = match self.title_regex.as_deref() {
+      Some(pattern) if !pattern.trim().is_empty() => Some(
+        Regex::new(pattern)
+          .map_err(|e| format!("Invalid title regex '{}': {}", pattern, e))?,
+      ),
+      _ => None,
+    };
+
+    let has_condition = !is_blank(&self.category)
+      || !self.title_keywords.is_empty()
+      || self.compiled_regex.is_some();
+    if self.action == RuleAction::Only && !has_condition {
+      return Err(
+        "An 'only' rule needs a category, title keyword or title regex.".into(),
+      );
+    }
+    Ok(())
+  }
+
+  fn targets(&self, event: &RuleEvent) -> bool {
+    let broadcaster_matches = match self.broadcaster.as_deref() {
+      Some(b) if !b.trim().is_empty() => {
+        let b = b.trim();
+        b == event.broadcaster_id
+          || b.eq_ignore_ascii_case(&event.broadcaster_name)
+      }
+      _ => true,
+    };
+    let type_matches = self.event_types.is_empty()
+      || self.event_types.iter().any(|t| t == &event.event_type);
+
+    broadcaster_matches && type_matches
+  }
+
+  fn conditions_hold(&self, event: &RuleEvent) -> bool {
+    let category_matches = match self.category.as_deref() {
+      Some(c) if !c.trim().is_empty() => {
+        c.trim().eq_ignore_ascii_case(event.category.trim())
+      }
+      _ => true,
+    };
+
+    let title = event.title.to_lowercase();
+    let keywords_match = self.title_keywords.is_empty()
+      || self
+        .title_keywords
+        .iter()
+        .any(|k| title.contains(&k.to_lowercase()));
+
+    let regex_matches = self
+      .compiled_regex
+      .as_ref()
+      .is_none_or(|re| re.is_match(&event.title));
+
+    category_matches && keywords_match && regex_matches
+  }
+
+  // Returns whether to notify, or None if the rule has no say.
+  fn decide(&self, event: &RuleEvent) -> Option<bool> {
+    if !self.enabled || !self.targets(event) {
+      return None;
+    }
+
+    let holds = self.conditions_hold(event);
+    match self.action {
+      RuleAction::Allow if holds => Some(true),
+      RuleAction::Deny if holds => Some(false),
+      RuleAction::Only => Some(holds),
+      _ => None,
+    }
+  }
+}
+
+impl Persist for RuleSet {
+  const FILE: &'static str = RULES_FILE;
+}
+
+impl RuleSet {
+  pub fn load(app: &AppHandle) -> Self {
+    let mut set: RuleSet = load_json(app, RULES_FILE);
+    // A rule that no longer compiles is switched off instead of dropped, so
+    // it's still there for the user to fix.
+    for rule in set.rules.iter_mut() {
+      if let Err(e) = rule.prepare() {
+        eprintln!("Disabling invalid rule '{}': {}", rule.name, e);
+        rule.enabled = false;
+      }
+    }
+    set
+  }
+
+  // Rules are checked in order and the first one with a say decides.
+  // Events no rule cares about are notified.
+  pub fn evaluate(&self, event: &RuleEvent) -> RuleVerdict {
+    for rule in &self.rules {
+      if let Some(notify) = rule.decide(event) {
+        return RuleVerdict {
+          notify,
+          rule_id: Some(rule.id.clone()),
+          rule_name: Some(rule.name.clone()),
+        };
+      }
+    }
+
+    RuleVerdict {
+      notify: true,
+      rule_id: None,
+      rule_name: None,
+    }
+  }
+
+  pub fn add(&mut self, mut rule: Rule) -> Result<Rule, String> {
+    rule.prepare()?;
+    rule.id = Uuid::new_v4().to_string();
+    self.rules.push(rule.clone());
+    Ok(rule)
+  }
+
+  pub fn update(&mut self, mut rule: Rule) -> Result<Rule, String> {
+    rule.prepare()?;
+    let existing = self
+      .rules
+      .iter_mut()
+      .find(|r| r.id == rule.id)
+      .ok_or_else(|| format!("No rule with id '{}'.", rule.id))?;
+    *existing = rule.clone();
+    Ok(rule)
+  }
+
+  pub fn remove(&mut self, id: &str) -> Result<(), String> {
+    let before = self.rules.len();
+    self.rules.retain(|r| r.id != id);
+    if self.rules.len() == before {
+      return Err(format!("No rule with id '{}'.", id));
+    }
+    Ok(())
+  }
+}
+
+pub fn evaluate_rules(app: &AppHandle, event: &RuleEvent) -> RuleVerdict {
+  match app.try_state::<Mutex<RuleSet>>() {
+    Some(rules) => rules.lock().unwrap().evaluate(event),
+    None => RuleSet::default().evaluate(event),
+  }
+}
@@ -0,0 +1,156 @@
+use chrono::{NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::launch::LaunchTarget;
+use crate::storage::{load_json, Persist};
+use crate::AppHandle;
+
+const SETTINGS_FILE: &str = "settings.json";
+const MAX_LIVE_COOLDOWN_MINUTES: u64 = 24 * 60;
+const MAX_CHANNEL_UPDATE_WINDOW_SECS: u64 = 60 * 60;
+
+// A weekly quiet period. `end` before `start` means it runs past midnight
+// into the next day.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuietPeriod {
+  pub days: Vec<Weekday>,
+  pub start: String,
+  pub end: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+  pub do_not_disturb: bool,
+  pub quiet_hours_enabled: bool,
+  pub quiet_hours: Vec<QuietPeriod>,
+  // A go-live this soon after the previous one is treated as a flap.
+  pub live_cooldown_minutes: u64,
+  // Channel updates within this window are merged into one notification.
+  pub channel_update_window_secs: u64,
+  // Broadcaster ids to notify about when their stream ends.
+  pub offline_notifications: Vec<String>,
+  pub launch_target: LaunchTarget,
+  // Per-streamer launch targets, by broadcaster id.
+  pub launch_overrides: HashMap<String, LaunchTarget>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      do_not_disturb: false,
+      quiet_hours_enabled: false,
+      quiet_hours: Vec::new(),
+      live_cooldown_minutes: 10,
+      channel_update_window_secs: 60,
+      offline_notifications: Vec::new(),
+      launch_target: LaunchTarget::default(),
+      launch_overrides: HashMap::new(),
+    }
+  }
+}
+
+pub fn parse_time(value: &str) -> Result<NaiveTime, String> {
+  NaiveTime::parse_from_str(value.trim(), "%H:%M")
+    .map_err(|_| format!("Invalid time '{}', expected HH:MM.", value))
+}
+
+impl QuietPeriod {
+  pub fn validate(&self) -> Result<(), String> {
+    if self.days.is_empty() {
+      return Err("A quiet period needs at least one day.".into());
+    }
+    let start = parse_time(&self.start)?;
+    let end = parse_time(&self.end)?;
+    if start == end {
+      return Err(
+        "A quiet period must not start and end at the same time.".into(),
+      );
+    }
+    Ok(())
+  }
+}
+
+impl Persist for Settings {
+  const FILE: &'static str = SETTINGS_FILE;
+}
+
+impl Settings {
+  pub fn load(app: &AppHandle) -> Self {
+    let mut settings: Settings = load_json(app, SETTINGS_FILE);
+    settings.drop_invalid();
+    settings
+  }
+
+  // Fixes up what a hand edit broke and keeps everything else, so one bad
+  // entry doesn't cost the user their other settings on the next save.
+  fn drop_invalid(&mut self) {
+    if self.live_cooldown_minutes > MAX_LIVE_COOLDOWN_MINUTES {
+      eprintln!(
+        "Capping the go-live cooldown at {} minutes.",
+        MAX_LIVE_COOLDOWN_MINUTES
+      );
+      self.live_cooldown_minutes = MAX_LIVE_COOLDOWN_MINUTES;
+    }
+    if self.channel_update_window_secs > MAX_CHANNEL_UPDATE_WINDOW_SECS {
+      eprintln!(
+        "Capping the channel update window at {} seconds.",
+        MAX_CHANNEL_UPDATE_WINDOW_SECS
+      );
+      self.channel_update_window_secs = MAX_CHANNEL_UPDATE_WINDOW_SECS;
+    }
+    self.quiet_hours.retain(|period| match period.validate() {
+      Ok(()) => true,
+      Err(e) => {
+        eprintln!("Dropping invalid quiet period: {}", e);
+        false
+      }
+    });
+    if let Err(e) = self.launch_target.validate() {
+      eprintln!("Resetting invalid launch target: {}", e);
+      self.launch_target = LaunchTarget::default();
+    }
+    self
+      .launch_overrides
+      .retain(|id, target| match target.validate() {
+        Ok(()) => true,
+        Err(e) => {
+          eprintln!("Dropping invalid launch target for {}: {}", id, e);
+          false
+        }
+      });
+  }
+
+  pub fn validate(&self) -> Result<(), String> {
+    if self.live_cooldown_minutes > MAX_LIVE_COOLDOWN_MINUTES {
+      return Err(format!(
+        "The go-live cooldown can be at most {} minutes.",
+        MAX_LIVE_COOLDOWN_MINUTES
+      ));
+    }
+    if self.channel_update_window_secs > MAX_CHANNEL_UPDATE_WINDOW_SECS {
+      return Err(format!(
+        "The channel update window can be at most {} seconds.",
+        MAX_CHANNEL_UPDATE_WINDOW_SECS
+      ));
+    }
+    for period in &self.quiet_hours {
+      period.validate()?;
+    }
+    self.launch_target.validate()?;
+    for target in self.launch_overrides.values() {
+      target.validate()?;
+    }
+    Ok(())
+  }
+}
+
+pub fn current_settings(app: &AppHandle) -> Settings {
+  match app.try_state::<Mutex<Settings>>() {
+    Some(settings) => settings.lock().unwrap().clone(),
+    None => Settings::default(),
+  }
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::storage::{load_json, save_json};
+use crate::AppHandle;
+
+const SNOOZE_FILE: &str = "snoozed.json";
+
+// Streamers we don't notify about, by broadcaster id. A snooze ends at its
+// expiry; a mute has none and lasts until it's lifted.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Snoozes {
+  pub until: HashMap<String, Option<DateTime<Utc>>>,
+}
+
+impl Snoozes {
+  pub fn load(app: &AppHandle) -> Self {
+    let mut snoozes: Snoozes = load_json(app, SNOOZE_FILE);
+    snoozes.prune();
+    snoozes
+  }
+
+  pub fn save(&self, app: &AppHandle) -> Result<(), String> {
+    save_json(app, SNOOZE_FILE, self)
+  }
+
+  // Drops expired snoozes and reports whether any were dropped.
+  pub fn prune(&mut self) -> bool {
+    let now = Utc::now();
+    let before = self.until.len();
+    self
+      .until
+      .retain(|_, until| until.is_none_or(|until| until > now));
+    self.until.len() != before
+  }
+
+  pub fn is_muted(&self, broadcaster_id: &str) -> bool {
+    matches!(self.until.get(broadcaster_id), Some(None))
+  }
+
+  pub fn is_silenced(&self, broadcaster_id: &str) -> bool {
+    match self.until.get(broadcaster_id) {
+      Some(Some(until)) => *until > Utc::now(),
+      Some(None) => true,
+      None => false,
+    }
+  }
+}
+
+// Whether the streamer is snoozed or muted. Expired snoozes are lifted here.
+pub fn is_silenced(app: &AppHandle, broadcaster_id: &str) -> bool {
+  let Some(state) = app.try_state::<Mutex<Snoozes>>() else {
+    return false;
+  };
+  let mut snoozes = state.lock().unwrap();
+  if snoozes.prune() {
+    if let Err(e) = snoozes.save(app) {
+      eprintln!("Failed to save lifted snoozes: {}", e);
+    }
+  }
+  snoozes.is_silenced(broadcaster_id)
+}
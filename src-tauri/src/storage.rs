@@ -0,0 +1,84 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::AppHandle;
+
+fn data_file(app: &AppHandle, file: &str) -> Result<PathBuf, String> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .map_err(|e| format!("Could not resolve app data dir: {}", e))?;
+  fs::create_dir_all(&dir)
+    .map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+  Ok(dir.join(file))
+}
+
+// Missing or unreadable files fall back to the default so a corrupt file
+// never keeps the app from starting.
+pub fn load_json<T: DeserializeOwned + Default>(
+  app: &AppHandle,
+  file: &str,
+) -> T {
+  let path = match data_file(app, file) {
+    Ok(path) => path,
+    Err(e) => {
+      eprintln!("{}", e);
+      return T::default();
+    }
+  };
+
+  match fs::read_to_string(&path) {
+    Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+      eprintln!("Ignoring invalid {}: {}", path.display(), e);
+      T::default()
+    }),
+    Err(_) => T::default(),
+  }
+}
+
+pub fn save_json<T: Serialize>(
+  app: &AppHandle,
+  file: &str,
+  value: &T,
+) -> Result<(), String> {
+  let path = data_file(app, file)?;
+  let contents = serde_json::to_string_pretty(value)
+    .map_err(|e| format!("Failed to serialize {}: {}", file, e))?;
+
+  // Write next to the target and rename so a crash can't leave half a file.
+  let tmp = path.with_extension("json.tmp");
+  fs::write(&tmp, contents)
+    .map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+  fs::rename(&tmp, &path)
+    .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+// Managed state that is kept in its own file in the app data dir.
+pub trait Persist: Serialize + Sized {
+  const FILE: &'static str;
+
+  fn save(&self, app: &AppHandle) -> Result<(), String> {
+    save_json(app, Self::FILE, self)
+  }
+}
+
+// Edits a copy of the managed state and only keeps it once it's saved, so a
+// failed save never leaves the app out of step with the file.
+pub fn edit_persisted<S, T>(
+  app: &AppHandle,
+  edit: impl FnOnce(&mut S) -> Result<T, String>,
+) -> Result<T, String>
+where
+  S: Persist + Clone + Send + 'static,
+{
+  let state = app.state::<Mutex<S>>();
+  let mut current = state.lock().unwrap();
+  let mut updated = current.clone();
+  let result = edit(&mut updated)?;
+  updated.save(app)?;
+  *current = updated;
+  Ok(result)
+}
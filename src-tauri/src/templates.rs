@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::Manager;
+use uuid::Uuid;
+
+use crate::storage::{load_json, Persist};
+use crate::AppHandle;
+
+const TEMPLATES_FILE: &str = "templates.json";
+
+pub const EVENT_TYPES: &[&str] = &["status", "channel_updated", "offline"];
+
+pub const PLACEHOLDERS: &[&str] = &[
+  "name",
+  "login",
+  "category",
+  "title",
+  "previous_title",
+  "previous_category",
+  "uptime",
+  "time",
+];
+
+// Heading and body for one event type, written with `{placeholder}`s. A
+// template with a `broadcaster` (id or name) wins over the generic one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Template {
+  #[serde(default)]
+  pub id: String,
+  pub event_type: String,
+  #[serde(default)]
+  pub broadcaster: Option<String>,
+  pub heading: String,
+  pub body: String,
+}
+
+// Values the placeholders expand to. Unknown values expand to nothing.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TemplateContext {
+  #[serde(default)]
+  pub name: String,
+  #[serde(default)]
+  pub login: String,
+  #[serde(default)]
+  pub category: String,
+  #[serde(default)]
+  pub title: String,
+  #[serde(default)]
+  pub previous_title: String,
+  #[serde(default)]
+  pub previous_category: String,
+  #[serde(default)]
+  pub uptime: String,
+  #[serde(default)]
+  pub time: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TemplatePreview {
+  pub heading: String,
+  pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TemplateSet {
+  pub templates: Vec<Template>,
+}
+
+impl TemplateContext {
+  // Stand-in values for previewing a template in the settings.
+  pub fn sample() -> Self {
+    Self {
+      name: "Streamer".into(),
+      login: "streamer".into(),
+      category: "Just Chatting".into(),
+      title: "Saturday hangout".into(),
+      previous_title: "Starting soon".into(),
+      previous_category: "Music".into(),
+      uptime: "1h 23m".into(),
+      time: chrono::Local::now().format("%H:%M").to_string(),
+    }
+  }
+
+  fn value(&self, placeholder: &str) -> Option<&str> {
+    let value = match placeholder {
+      "name" => &self.name,
+      "login" => &self.login,
+      "category" => &self.category,
+      "title" => &self.title,
+      "previous_title" => &self.previous_title,
+      "previous_category" => &self.previous_category,
+      "uptime" => &self.uptime,
+      "time" => &self.time,
+      _ => return None,
+    };
+    Some(value)
+  }
+}
+
+// Expands `{placeholder}`s in `text`. `{{` and `}}` stand for literal braces.
+fn expand(text: &str, context: &TemplateContext) -> Result<String, String> {
+  let mut out = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '{' if chars.peek() == Some(&'{') => {
+        chars.next();
+        out.push('{');
+      }
+      '}' if chars.peek() == Some(&'}') => {
+        chars.next();
+        out.push('}');
+      }
+      '{' => {
+        let mut placeholder = String::new();
+        loop {
+          match chars.next() {
+            Some('}') => break,
+            Some(c) => placeholder.push(c),
+            None => {
+              return Err(format!("Unclosed '{{' in '{}'.", text));
+            }
+          }
+        }
+        let value = context.value(placeholder.trim()).ok_or_else(|| {
+          format!(
+            "Unknown placeholder '{{{}}}'. Available: {}.",
+            placeholder,
+            PLACEHOLDERS.join(", ")
+          )
+        })?;
+        out.push_str(value);
+      }
+      '}' => return Err(format!("Unmatched '}}' in '{}'.", text)),
+      c => out.push(c),
+    }
+  }
+  Ok(out)
+}
+
+impl Template {
+  pub fn validate(&self) -> Result<(), String> {
+    if !EVENT_TYPES.contains(&self.event_type.as_str()) {
+      return Err(format!(
+        "Unknown event type '{}'. Expected one of: {}.",
+        self.event_type,
+        EVENT_TYPES.join(", ")
+      ));
+    }
+    if self.heading.trim().is_empty() {
+      return Err("A template needs a heading.".into());
+    }
+
+    let context = TemplateContext::default();
+    expand(&self.heading, &context)?;
+    expand(&self.body, &context)?;
+    Ok(())
+  }
+
+  pub fn render(&self, context: &TemplateContext) -> TemplatePreview {
+    // Templates are validated on save, so this only trips on hand edits.
+    let render = |text: &str| {
+      expand(text, context).unwrap_or_else(|e| {
+        eprintln!("Failed to render template '{}': {}", self.id, e);
+        text.to_string()
+      })
+    };
+    TemplatePreview {
+      heading: render(&self.heading),
+      body: render(&self.body),
+    }
+  }
+
+  fn broadcaster(&self) -> Option<&str> {
+    self
+      .broadcaster
+      .as_deref()
+      .map(str::trim)
+      .filter(|b| !b.is_empty())
+  }
+
+  fn matches_broadcaster(&self, broadcaster_id: &str, name: &str) -> bool {
+    self
+      .broadcaster()
+      .is_some_and(|b| b == broadcaster_id || b.eq_ignore_ascii_case(name))
+  }
+}
+
+impl Persist for TemplateSet {
+  const FILE: &'static str = TEMPLATES_FILE;
+}
+
+impl TemplateSet {
+  pub fn load(app: &AppHandle) -> Self {
+    let set: TemplateSet = load_json(app, TEMPLATES_FILE);
+    // Templates that fail to validate stay in the file for the user to fix;
+    // `find` passes over them.
+    for template in &set.templates {
+      if let Err(e) = template.validate() {
+        eprintln!("Ignoring invalid template '{}': {}", template.id, e);
+      }
+    }
+    set
+  }
+
+  // The streamer's own template if there is one, else the generic one.
+  // Invalid templates are skipped.
+  pub fn find(
+    &self,
+    event_type: &str,
+    broadcaster_id: &str,
+    name: &str,
+  ) -> Option<&Template> {
+    let candidates = || {
+      self
+        .templates
+        .iter()
+        .filter(move |t| t.event_type == event_type && t.validate().is_ok())
+    };
+    candidates()
+      .find(|t| t.matches_broadcaster(broadcaster_id, name))
+      .or_else(|| candidates().find(|t| t.broadcaster().is_none()))
+  }
+
+  fn check_unique(&self, template: &Template) -> Result<(), String> {
+    let clash = self.templates.iter().any(|t| {
+      t.id != template.id
+        && t.event_type == template.event_type
+        && t.broadcaster().map(str::to_lowercase)
+          == template.broadcaster().map(str::to_lowercase)
+    });
+    if clash {
+      return Err(format!(
+        "There is already a '{}' template for {}.",
+        template.event_type,
+        template.broadcaster().unwrap_or("all streamers")
+      ));
+    }
+    Ok(())
+  }
+
+  pub fn add(&mut self, mut template: Template) -> Result<Template, String> {
+    template.validate()?;
+    template.id = Uuid::new_v4().to_string();
+    self.check_unique(&template)?;
+    self.templates.push(template.clone());
+    Ok(template)
+  }
+
+  pub fn update(&mut self, template: Template) -> Result<Template, String> {
+    template.validate()?;
+    self.check_unique(&template)?;
+    let existing = self
+      .templates
+      .iter_mut()
+      .find(|t| t.id == template.id)
+      .ok_or_else(|| format!("No template with id '{}'.", template.id))?;
+    *existing = template.clone();
+    Ok(template)
+  }
+
+  pub fn remove(&mut self, id: &str) -> Result<(), String> {
+    let before = self.templates.len();
+    self.templates.retain(|t| t.id != id);
+    if self.templates.len() == before {
+      return Err(format!("No template with id '{}'.", id));
+    }
+    Ok(())
+  }
+}
+
+pub fn render_template(
+  app: &AppHandle,
+  event_type: &str,
+  broadcaster_id: &str,
+  context: &TemplateContext,
+) -> Option<TemplatePreview> {
+  let templates = app.try_state::<Mutex<TemplateSet>>()?;
+  let templates = templates.lock().unwrap();
+  templates
+    .find(event_type, broadcaster_id, &context.name)
+    .map(|template| template.render(context))
+}
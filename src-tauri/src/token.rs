@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tauri::Manager;
+use tokio::sync::RwLock;
+
+use crate::{
+  config::AppConfig,
+  oauth::{refresh_access_token, validate_access_token},
+  util::load_secret,
+  AppHandle,
+};
+
+// Refresh a little before Twitch would expire the token.
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+// Twitch requires long-running apps to validate their token every hour.
+const REVALIDATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+static LIFECYCLE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Default)]
+pub struct SharedToken(pub Arc<RwLock<String>>);
+
+impl SharedToken {
+  pub fn new(token: String) -> Self {
+    Self(Arc::new(RwLock::new(token)))
+  }
+}
+
+pub async fn set_access_token(app: &AppHandle, token: String) {
+  *app.state::<SharedToken>().0.write().await = token.clone();
+
+  if let Some(auth_state) = app.try_state::<Mutex<Option<String>>>() {
+    let mut guard = auth_state.lock().unwrap();
+    if guard.is_some() {
+      *guard = Some(token);
+    }
+  }
+}
+
+pub async fn refresh_and_store(app: &AppHandle) -> anyhow::Result<String> {
+  let config = app.state::<AppConfig>().inner().clone();
+
+  let new_token = tokio::task::spawn_blocking(move || {
+    let refresh_token = load_secret("refresh_token")
+      .ok_or_else(|| anyhow::anyhow!("No refresh token available"))?;
+    refresh_access_token(&config, &refresh_token)
+      .map_err(|e| anyhow::anyhow!("Failed to refresh access token: {:?}", e))
+  })
+  .await??;
+
+  set_access_token(app, new_token.clone()).await;
+  Ok(new_token)
+}
+
+pub fn start_token_lifecycle(app: AppHandle) {
+  if LIFECYCLE_RUNNING.swap(true, Ordering::SeqCst) {
+    return;
+  }
+
+  tauri::async_runtime::spawn(async move {
+    loop {
+      let wait = check_token(&app).await;
+      println!("Next token check in {} minutes.", wait.as_secs() / 60);
+      tokio::time::sleep(wait).await;
+    }
+  });
+}
+
+async fn check_token(app: &AppHandle) -> Duration {
+  let config = app.state::<AppConfig>().inner().clone();
+  let token = app.state::<SharedToken>().0.read().await.clone();
+  if token.is_empty() {
+    return RETRY_INTERVAL;
+  }
+
+  let validation = tokio::task::spawn_blocking(move || {
+    validate_access_token(&config, &token)
+      .map(|resp| resp.map(|r| r.expires_in))
+      .map_err(|e| format!("{:?}", e))
+  })
+  .await;
+
+  let expires_in = match validation {
+    // Tokens that never expire report `expires_in: 0`.
+    Ok(Ok(Some(expires_in))) => {
+      expires_in.filter(|secs| *secs > 0).map(Duration::from_secs)
+    }
+    Ok(Ok(None)) => {
+      println!("Access token is no longer valid, refreshing.");
+      Some(Duration::ZERO)
+    }
+    Ok(Err(e)) => {
+      eprintln!("Failed to validate access token: {}", e);
+      return RETRY_INTERVAL;
+    }
+    Err(e) => {
+      eprintln!("Token validation task failed: {}", e);
+      return RETRY_INTERVAL;
+    }
+  };
+
+  match expires_in {
+    Some(remaining) if remaining <= REFRESH_MARGIN => {
+      match refresh_and_store(app).await {
+        Ok(_) => {
+          println!("Access token refreshed ahead of expiry.");
+          // Validate the new token straight away to learn its lifetime.
+          Duration::from_secs(1)
+        }
+        Err(e) => {
+          eprintln!("Proactive token refresh failed: {}", e);
+          RETRY_INTERVAL
+        }
+      }
+    }
+    Some(remaining) => (remaining - REFRESH_MARGIN).min(REVALIDATE_INTERVAL),
+    None => REVALIDATE_INTERVAL,
+  }
+}
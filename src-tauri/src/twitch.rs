@@ -1,6 +1,7 @@
-use dotenvy_macro::dotenv;
+use crate::command::Broadcasters;
+use crate::config::AppConfig;
 use reqwest::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 #[derive(Serialize)]
@@ -8,32 +9,56 @@ pub struct Broadcaster {
   pub broadcaster_id: u64,
 }
 
-pub async fn register_streamers_webhook(streamers: Vec<Broadcaster>) {
-  let base_url = dotenv!("BASE_URI");
-  let webhook_url = format!("{}/register", base_url);
+pub async fn register_streamers_webhook(
+  config: &AppConfig,
+  streamers: Vec<Broadcaster>,
+) -> Result<(), String> {
+  let webhook_url = format!("{}/register", config.base_uri);
   let data =
     serde_json::to_string(&streamers).expect("Failed to serialize json.");
   let client = Client::new();
-  match client.post(webhook_url).body(data).send().await {
-    Ok(resp) => {
-      if !resp.status().is_success() {
-        eprintln!(
-          "Failed to register hook. Status: {}, Error: {}",
-          resp.status(),
-          resp.text().await.unwrap()
-        )
-      }
-    }
-    Err(e) => panic!("{}", e),
-  };
+  let resp = client
+    .post(webhook_url)
+    .body(data)
+    .send()
+    .await
+    .map_err(|e| format!("register request err: {}", e))?;
+
+  if !resp.status().is_success() {
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    return Err(format!(
+      "Failed to register hook. Status: {}, Error: {}",
+      status, body
+    ));
+  }
+  Ok(())
+}
+
+pub async fn fetch_streamer_snapshots(
+  config: &AppConfig,
+  broadcaster_ids: &[String],
+) -> Result<Vec<Broadcasters>, String> {
+  let client = Client::new();
+
+  let resp = client
+    .post(format!("{}/streamers/fetch-all", config.base_uri))
+    .json(&broadcaster_ids)
+    .send()
+    .await
+    .map_err(|e| format!("fetch-all request err: {}", e))?;
+
+  resp
+    .json::<Vec<Broadcasters>>()
+    .await
+    .map_err(|e| format!("json parse: {}", e))
 }
 
 pub async fn fetch_followed_streamers(
+  config: &AppConfig,
   token: &str,
   user_id: &str,
 ) -> Result<Vec<String>, String> {
-  let client_id = dotenv!("CLIENT_ID");
-
   let client = Client::builder()
     .build()
     .map_err(|e| format!("reqwest build: {}", e))?;
@@ -43,8 +68,8 @@ pub async fn fetch_followed_streamers(
 
   loop {
     let mut url = format!(
-      "https://api.twitch.tv/helix/channels/followed?user_id={}&first=100",
-      user_id
+      "{}/helix/channels/followed?user_id={}&first=100",
+      config.twitch_api_uri, user_id
     );
 
     if let Some(cursor) = &after {
@@ -53,7 +78,7 @@ pub async fn fetch_followed_streamers(
 
     let resp = client
       .get(&url)
-      .header("Client-Id", client_id)
+      .header("Client-Id", &config.client_id)
       .header("Authorization", format!("Bearer {}", token))
       .send()
       .await
@@ -93,3 +118,50 @@ pub async fn fetch_followed_streamers(
   collected.dedup();
   Ok(collected)
 }
+
+#[derive(Deserialize, Debug)]
+pub struct TwitchUser {
+  pub id: String,
+  pub login: String,
+  pub display_name: String,
+}
+
+#[derive(Deserialize)]
+struct TwitchUsersResponse {
+  data: Vec<TwitchUser>,
+}
+
+// Looks a channel up by login, or by id when given only digits.
+pub async fn fetch_user(
+  config: &AppConfig,
+  token: &str,
+  channel: &str,
+) -> Result<Option<TwitchUser>, String> {
+  let key = if channel.chars().all(|c| c.is_ascii_digit()) {
+    "id"
+  } else {
+    "login"
+  };
+
+  let client = Client::new();
+  let resp = client
+    .get(format!("{}/helix/users", config.twitch_api_uri))
+    .query(&[(key, channel)])
+    .header("Client-Id", &config.client_id)
+    .header("Authorization", format!("Bearer {}", token))
+    .send()
+    .await
+    .map_err(|e| format!("twitch request err: {}", e))?;
+
+  if !resp.status().is_success() {
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    return Err(format!("twitch API error {}: {}", status, body));
+  }
+
+  let body: TwitchUsersResponse = resp
+    .json()
+    .await
+    .map_err(|e| format!("json parse: {}", e))?;
+  Ok(body.data.into_iter().next())
+}
@@ -1,10 +1,13 @@
-use tauri::AppHandle;
+use tauri::Manager;
 
 use crate::{
-  appsync::start_ws_client,
+  appsync::{start_ws_client, stop_ws_client},
   command::fetch_streamers,
+  config::AppConfig,
   oauth::{refresh_access_token, validate_access_token},
+  token::start_token_lifecycle,
   twitch::{fetch_followed_streamers, register_streamers_webhook, Broadcaster},
+  AppHandle,
 };
 
 pub fn load_secret(name: &str) -> Option<String> {
@@ -35,9 +38,10 @@ pub fn spawn_new_user(
   token_ws: String,
   app: AppHandle,
 ) {
+  let config = app.state::<AppConfig>().inner().clone();
   tauri::async_runtime::spawn(async move {
     let streamers: Vec<Broadcaster> =
-      match fetch_followed_streamers(&access_token, &user).await {
+      match fetch_followed_streamers(&config, &access_token, &user).await {
         Ok(ids) => ids
           .into_iter()
           .filter_map(|s| {
@@ -48,53 +52,60 @@ pub fn spawn_new_user(
           .collect(),
         Err(e) => panic!("{}", e),
       };
-    register_streamers_webhook(streamers).await;
+    if let Err(e) = register_streamers_webhook(&config, streamers).await {
+      eprintln!("{}", e);
+    }
     fetch_streamers(app.clone());
 
+    start_token_lifecycle(app.clone());
+    // A previous session may still be running if the user logged in again.
+    let _ = stop_ws_client();
     if let Err(e) = start_ws_client(app, token_ws) {
       eprintln!("start_ws_client failed after registering webhook: {:?}", e)
     }
   });
 }
 
-pub fn check_validitiy_token() -> Option<String> {
+pub fn check_validitiy_token(config: &AppConfig) -> Option<String> {
   return match load_secret("access_token") {
-    Some(existing_token) => match validate_access_token(&existing_token) {
-      Ok(Some(_resp)) => Some(existing_token),
-      Ok(None) => {
-        eprintln!(
-          "Access token invalid (401). Attempting refresh if possible..."
-        );
-        if let Some(refresh_token) = load_secret("refresh_token") {
-          match refresh_access_token(&refresh_token) {
-            Ok(_) => match load_secret("access_token") {
-              Some(new_access) => {
-                eprintln!("Token refresh succeeded; starting WS client with refreshed token");
-                Some(new_access)
-              }
-              None => {
-                eprintln!("Token refresh succeeded but new access token not found in keyring");
+    Some(existing_token) => {
+      match validate_access_token(config, &existing_token) {
+        Ok(Some(_resp)) => Some(existing_token),
+        Ok(None) => {
+          eprintln!(
+            "Access token invalid (401). Attempting refresh if possible..."
+          );
+          if let Some(refresh_token) = load_secret("refresh_token") {
+            match refresh_access_token(config, &refresh_token) {
+              Ok(_) => match load_secret("access_token") {
+                Some(new_access) => {
+                  eprintln!("Token refresh succeeded; starting WS client with refreshed token");
+                  Some(new_access)
+                }
+                None => {
+                  eprintln!("Token refresh succeeded but new access token not found in keyring");
+                  None
+                }
+              },
+              Err(err) => {
+                eprintln!("Token refresh failed: {:?}", err);
                 None
               }
-            },
-            Err(err) => {
-              eprintln!("Token refresh failed: {:?}", err);
-              None
             }
+          } else {
+            eprintln!("No refresh token available to refresh access token");
+            None
           }
-        } else {
-          eprintln!("No refresh token available to refresh access token");
+        }
+        Err(err) => {
+          eprintln!(
+            "validate_access_token returned error during startup: {:?}",
+            err
+          );
           None
         }
       }
-      Err(err) => {
-        eprintln!(
-          "validate_access_token returned error during startup: {:?}",
-          err
-        );
-        None
-      }
-    },
+    }
     None => {
       eprintln!("No access token found in keyring; WS client will not start");
       None
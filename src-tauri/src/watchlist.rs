@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::storage::{load_json, save_json};
+use crate::AppHandle;
+
+const WATCH_LIST_FILE: &str = "watchlist.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchedChannel {
+  pub broadcaster_id: String,
+  pub login: String,
+  pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct WatchList {
+  pub channels: Vec<WatchedChannel>,
+}
+
+impl WatchList {
+  pub fn load(app: &AppHandle) -> Self {
+    load_json(app, WATCH_LIST_FILE)
+  }
+
+  pub fn save(&self, app: &AppHandle) -> Result<(), String> {
+    save_json(app, WATCH_LIST_FILE, self)
+  }
+
+  pub fn contains(&self, broadcaster_id: &str) -> bool {
+    self
+      .channels
+      .iter()
+      .any(|c| c.broadcaster_id == broadcaster_id)
+  }
+}
+
+pub fn watched_ids(app: &AppHandle) -> Vec<String> {
+  match app.try_state::<Mutex<WatchList>>() {
+    Some(list) => list
+      .lock()
+      .unwrap()
+      .channels
+      .iter()
+      .map(|c| c.broadcaster_id.clone())
+      .collect(),
+    None => Vec::new(),
+  }
+}
+
+// Adds the manually watched channels to a list of followed broadcaster ids.
+pub fn merge_watched(
+  app: &AppHandle,
+  mut broadcaster_ids: Vec<String>,
+) -> Vec<String> {
+  broadcaster_ids.extend(watched_ids(app));
+  broadcaster_ids.sort();
+  broadcaster_ids.dedup();
+  broadcaster_ids
+}
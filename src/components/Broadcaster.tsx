@@ -5,7 +5,11 @@ import { invoke } from '@tauri-apps/api/core';
 
 export const Broadcaster = (broadcaster: BroadcasterType) => {
   const openStream = (broadcaster_name: string) => {
-    invoke('open_broadcaster_url', { broadcasterName: broadcaster_name });
+    invoke('open_broadcaster_url', {
+      broadcasterName: broadcaster_name,
+      broadcasterId: broadcaster.broadcaster_id,
+      broadcasterLogin: broadcaster.broadcaster_login,
+    });
   };
   return (
     <div
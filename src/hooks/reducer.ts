@@ -1,13 +1,15 @@
 import { Broadcaster, Broadcasters, Update } from '@/views/List';
 
 type Action =
-  | { type: 'SET_LISTS'; online: Broadcaster[]; offline: Broadcaster[] }
-  | { type: 'APPLY_UPDATE'; update: Update };
+  | { type: 'SET_LISTS'; favorites: Broadcaster[]; online: Broadcaster[]; offline: Broadcaster[] }
+  | { type: 'APPLY_UPDATE'; update: Update }
+  | { type: 'ADD_STREAMERS'; streamers: Broadcaster[] }
+  | { type: 'REMOVE_STREAMERS'; ids: string[] };
 
 export const reducer = (state: Broadcasters, action: Action): Broadcasters => {
   switch (action.type) {
     case 'SET_LISTS':
-      return { online: action.online, offline: action.offline };
+      return { favorites: action.favorites ?? [], online: action.online, offline: action.offline };
 
     case 'APPLY_UPDATE': {
       const update = action.update;
@@ -20,9 +22,20 @@ export const reducer = (state: Broadcasters, action: Action): Broadcasters => {
 
       const targetIsOnline = Boolean(update.is_live);
 
+      // Favorites stay in their own group whether they're live or not.
+      if (state.favorites.some((b) => b.broadcaster_id === id)) {
+        return {
+          ...state,
+          favorites: state.favorites.map((b) =>
+            b.broadcaster_id === id ? { ...b, ...update, is_live: update.is_live ?? b.is_live } : b,
+          ),
+        };
+      }
+
       const mergedFromExisting = (existing?: Broadcaster) => ({
         broadcaster_id: id,
         broadcaster_name: update.broadcaster_name ?? existing?.broadcaster_name ?? '',
+        broadcaster_login: update.broadcaster_login ?? existing?.broadcaster_login,
         category: update.category ?? existing?.category ?? '',
         title: update.title ?? existing?.title ?? '',
         is_live: Boolean(update.is_live),
@@ -43,6 +56,7 @@ export const reducer = (state: Broadcasters, action: Action): Broadcasters => {
       if (targetIsOnline && inOffline) {
         const item = { ...mergedFromExisting(inOffline), is_live: true };
         return {
+          ...state,
           online: [item, ...removeById(state.online)],
           offline: removeById(state.offline),
         };
@@ -50,6 +64,7 @@ export const reducer = (state: Broadcasters, action: Action): Broadcasters => {
       if (!targetIsOnline && inOnline) {
         const item = { ...mergedFromExisting(inOnline), is_live: false };
         return {
+          ...state,
           offline: [item, ...removeById(state.offline)],
           online: removeById(state.online),
         };
@@ -58,17 +73,35 @@ export const reducer = (state: Broadcasters, action: Action): Broadcasters => {
       const newItem = { ...mergedFromExisting(undefined), is_live: targetIsOnline };
       if (targetIsOnline) {
         return {
+          ...state,
           online: [newItem, ...state.online.filter((b) => b.broadcaster_id !== id)],
           offline: state.offline,
         };
       } else {
         return {
+          ...state,
           offline: [newItem, ...state.offline.filter((b) => b.broadcaster_id !== id)],
           online: state.online,
         };
       }
     }
 
+    case 'ADD_STREAMERS': {
+      const ids = new Set(action.streamers.map((s) => s.broadcaster_id));
+      const keep = (arr: Broadcaster[]) => arr.filter((x) => !ids.has(x.broadcaster_id));
+      return {
+        favorites: keep(state.favorites),
+        online: [...keep(state.online), ...action.streamers.filter((s) => s.is_live)],
+        offline: [...keep(state.offline), ...action.streamers.filter((s) => !s.is_live)],
+      };
+    }
+
+    case 'REMOVE_STREAMERS': {
+      const ids = new Set(action.ids);
+      const keep = (arr: Broadcaster[]) => arr.filter((x) => !ids.has(x.broadcaster_id));
+      return { favorites: keep(state.favorites), online: keep(state.online), offline: keep(state.offline) };
+    }
+
     default:
       return state;
   }
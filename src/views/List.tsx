@@ -9,6 +9,7 @@ import { Dispatch, SetStateAction, useEffect, useReducer } from 'react';
 export type Broadcaster = {
   broadcaster_id: string;
   broadcaster_name: string;
+  broadcaster_login?: string;
   category: string;
   title: string;
   is_live: boolean;
@@ -16,6 +17,7 @@ export type Broadcaster = {
 };
 
 export type Broadcasters = {
+  favorites: Broadcaster[];
   online: Broadcaster[];
   offline: Broadcaster[];
 };
@@ -23,6 +25,7 @@ export type Broadcasters = {
 export type Update = {
   broadcaster_id: string;
   broadcaster_name: string;
+  broadcaster_login?: string;
   category: string;
   title: string;
   is_live: boolean;
@@ -30,6 +33,15 @@ export type Update = {
   updated: string;
 };
 
+export type FollowsAdded = {
+  broadcaster_ids: string[];
+  streamers: Broadcaster[];
+};
+
+export type FollowsRemoved = {
+  broadcaster_ids: string[];
+};
+
 export type BroadcastUpdate = {
   broadcaster_id: string;
   payload: Update;
@@ -42,13 +54,13 @@ interface IListProps {
 }
 
 export const List = ({ loading, setLoading }: IListProps) => {
-  const [state, dispatch] = useReducer(reducer, { online: [], offline: [] });
+  const [state, dispatch] = useReducer(reducer, { favorites: [], online: [], offline: [] });
 
   useEffect(() => {
     let unlistenStreamers: UnlistenFn;
     listen('streamers:fetched', (event) => {
-      const { offline, online } = event.payload as Broadcasters;
-      dispatch({ type: 'SET_LISTS', online: online, offline: offline });
+      const { favorites, offline, online } = event.payload as Broadcasters;
+      dispatch({ type: 'SET_LISTS', favorites: favorites, online: online, offline: offline });
       setLoading(false);
     }).then((fn) => {
       unlistenStreamers = fn;
@@ -74,6 +86,27 @@ export const List = ({ loading, setLoading }: IListProps) => {
     };
   });
 
+  useEffect(() => {
+    let unlistenAdded: UnlistenFn;
+    let unlistenRemoved: UnlistenFn;
+    listen('follows:added', (event) => {
+      const { streamers } = event.payload as FollowsAdded;
+      dispatch({ type: 'ADD_STREAMERS', streamers: streamers });
+    }).then((fn) => {
+      unlistenAdded = fn;
+    });
+    listen('follows:removed', (event) => {
+      const { broadcaster_ids } = event.payload as FollowsRemoved;
+      dispatch({ type: 'REMOVE_STREAMERS', ids: broadcaster_ids });
+    }).then((fn) => {
+      unlistenRemoved = fn;
+    });
+    return () => {
+      unlistenAdded && unlistenAdded();
+      unlistenRemoved && unlistenRemoved();
+    };
+  }, []);
+
   return (
     <div className="w-full overflow-x-hidden">
       {loading ? (
@@ -82,6 +115,23 @@ export const List = ({ loading, setLoading }: IListProps) => {
         </div>
       ) : (
         <div id="broadcasters" className="flex flex-col ml-2">
+          {state.favorites.length > 0 && (
+            <>
+              {state.favorites.map((streamer) => (
+                <div key={streamer.broadcaster_id} className="flex w-full">
+                  <div className="flex-1 min-w-0">
+                    <Broadcaster {...streamer} />
+                  </div>
+                  {streamer.is_live && (
+                    <div className="flex-shrink-0 content-center mr-2 mb-3 mt-2">
+                      <Live />
+                    </div>
+                  )}
+                </div>
+              ))}
+              <Separator />
+            </>
+          )}
           {state.online
             .sort((a, b) => a.broadcaster_name.localeCompare(b.broadcaster_name))
             .map((streamer) => (